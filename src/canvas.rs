@@ -0,0 +1,398 @@
+use rusttype::{point, OutlineBuilder, Scale};
+
+use crate::{color::{BlendMode, Color, PixelFormat}, damage::Damage, font::FontSet, glyph_cache::{GlyphCache, SUBPIXEL_STEPS}, paint::Paint, path::{FillRule, Path}, pixel_util::{dist_to_arc, dist_to_circle, dist_to_ellipse, dist_to_line, dist_to_rounded_rect, CornerRadii, Rect, Vector2}, layer::Layer, raster::{rasterize, Mask}, transform::Transform};
+
+/// A stroke along the inside of a shape's outline.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Border {
+    pub width: f32,
+    pub paint: Paint,
+}
+
+impl Border {
+    pub fn new(width: f32, paint: Paint) -> Self {
+        Self {width, paint}
+    }
+}
+
+/// An offscreen pixel buffer with every drawing primitive, ARGB8888 unless created [`Canvas::with_format`].
+///
+/// Pixels are stored premultiplied and drawn with source-over blending unless another
+/// [`BlendMode`] is set. Coordinates go through the current [`Transform`], see [`Canvas::save`].
+/// A canvas doesn't need a compositor, widgets present one to their surface
+/// and it can be rendered and inspected on its own, e.g. in tests.
+pub struct Canvas {
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    blend_mode: BlendMode,
+    pub(crate) buffer: Vec<u8>,
+    pub(crate) damage: Damage,
+    clip_stack: Vec<Rect>,
+    pub(crate) transform: Transform,
+    pub(crate) transform_stack: Vec<Transform>,
+    pub(crate) layers: Vec<Layer>,
+    glyph_cache: GlyphCache,
+}
+
+impl Canvas {
+    /// Creates a transparent canvas, damaged as a whole since nothing was presented from it yet.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self::with_format(width, height, PixelFormat::Argb8888)
+    }
+
+    pub fn with_format(width: u32, height: u32, format: PixelFormat) -> Self {
+        let mut damage = Damage::new();
+        damage.add(Rect::new(0, 0, width as i32, height as i32));
+        Self {width, height, format, blend_mode: BlendMode::default(), buffer: vec![0u8; (width * height * 4) as usize], damage, clip_stack: vec![], transform: Transform::IDENTITY, transform_stack: vec![], layers: vec![], glyph_cache: GlyphCache::default()}
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.blend_mode = mode;
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The raw premultiplied pixels in the [`PixelFormat::storage`] of the canvas' format,
+    /// row by row with a stride of `width * 4` bytes. While a layer is pushed these are the layer's pixels.
+    pub fn data(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Mutable access to the raw pixels, changes have to be reported with [`Canvas::damage`].
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.buffer
+    }
+
+    /// The colour at a pixel, with straight alpha.
+    pub fn pixel(&self, x: u32, y: u32) -> Option<Color> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let pixel_index = (y as usize * self.width as usize + x as usize) * 4;
+        let p = &self.buffer[pixel_index..pixel_index + 4];
+        Some(self.buffer_format().decode([p[0], p[1], p[2], p[3]]).unpremultiply())
+    }
+
+    /// The format of the pixels in the buffer, layers keep their alpha even on opaque canvases.
+    pub(crate) fn buffer_format(&self) -> PixelFormat {
+        if self.layers.is_empty() { self.format.storage() } else { PixelFormat::Argb8888 }
+    }
+
+    fn bounds(&self) -> Rect {
+        Rect::new(0, 0, self.width as i32, self.height as i32)
+    }
+
+    /// Restricts drawing to `rect` within the current clip until the matching [`Canvas::pop_clip`].
+    pub fn push_clip(&mut self, rect: Rect) {
+        let clip = self.clip().intersection(&rect);
+        self.clip_stack.push(clip);
+    }
+
+    pub fn pop_clip(&mut self) -> Option<Rect> {
+        self.clip_stack.pop()
+    }
+
+    /// The region primitives may draw to, never larger than the canvas.
+    pub fn clip(&self) -> Rect {
+        self.clip_stack.last().copied().unwrap_or(self.bounds())
+    }
+
+    /// Blends a premultiplied colour into the pixel at `pixel_index`.
+    pub(crate) fn blend(&mut self, pixel_index: usize, src: Color) {
+        let format = self.buffer_format();
+        let p = &mut self.buffer[pixel_index..pixel_index + 4];
+        let dst = format.decode([p[0], p[1], p[2], p[3]]);
+        p.copy_from_slice(&format.encode(self.blend_mode.apply(src, dst)));
+    }
+
+    /// Blends `paint` scaled by the coverage of every pixel in the mask.
+    pub(crate) fn fill_mask(&mut self, paint: &Paint, mask: &Mask) {
+        let inverse = self.transform.inverse().unwrap_or_default();
+        let area = mask.area;
+        for y in area.y..area.bottom() {
+            for x in area.x..area.right() {
+                let coverage = mask.get(x, y);
+                if coverage > 0.0 {
+                    let pixel_index = (y as usize * self.width as usize + x as usize) * 4;
+                    let color = paint.sample(inverse.apply(Vector2::new(x as f32 + 0.5, y as f32 + 0.5)));
+                    self.blend(pixel_index, color.lerp(Color::TRANSPARENT, coverage));
+                }
+            }
+        }
+
+        self.damage(area);
+    }
+
+    /// Fills polygons given in current coordinates.
+    pub(crate) fn fill_polygons(&mut self, paint: &Paint, polygons: &[Vec<Vector2>], rule: FillRule) {
+        let transform = self.transform;
+        let polygons: Vec<Vec<Vector2>> = polygons.iter().map(|p| p.iter().map(|v| transform.apply(*v)).collect()).collect();
+        let mask = rasterize(&polygons, rule, self.clip());
+        self.fill_mask(paint, &mask);
+    }
+
+    /// Marks a region as changed, only changed regions are sent to the compositor.
+    pub fn damage(&mut self, rect: Rect) {
+        let bounds = self.bounds();
+        self.damage.add(rect.intersection(&bounds));
+    }
+
+    pub fn damaged(&self) -> &[Rect] {
+        self.damage.rects()
+    }
+
+    /// Returns the regions changed since the last call.
+    pub fn take_damage(&mut self) -> Vec<Rect> {
+        self.damage.take()
+    }
+
+    /// How many glyphs [`Canvas::draw_text`] keeps rasterized.
+    pub fn cached_glyphs(&self) -> usize {
+        self.glyph_cache.len()
+    }
+
+    /// Frees every cached glyph, e.g. after drawing text that won't be shown again.
+    pub fn clear_glyph_cache(&mut self) {
+        self.glyph_cache.clear();
+    }
+
+    /// Draws `text` with its baseline starting at `pos`, `colorf` picks the paint of every character.
+    ///
+    /// `font` is a [`Font`](rusttype::Font) or a [`FontSet`] whose fallbacks cover characters the first font lacks.
+    /// Glyphs are cached per canvas, so text that is redrawn often is only rasterized once.
+    pub fn draw_text<'f, S: Into<FontSet<'f>>, F: Fn(u32, char) -> Paint>(&mut self, text: String, pos: Vector2, size: f32, font: S, colorf: F, bg: Paint) {
+        let fonts = font.into();
+        let t = self.transform;
+        if !(t.is_axis_aligned() && t.a == t.d && t.a > 0.0) {
+            // Rotated, skewed or stretched text is filled from the glyph outlines
+            return self.draw_text_outlines(text, pos, size, &fonts, colorf, bg);
+        }
+        let inverse = t.inverse().unwrap_or_default();
+        let (pos, size) = (t.apply(pos), size * t.a);
+
+        let clip = self.clip();
+        let steps = SUBPIXEL_STEPS as f32;
+        let mut cache = std::mem::take(&mut self.glyph_cache);
+        let mut chars = text.chars();
+        let mut touched: Option<Rect> = None;
+
+        for (index, (font, glyph)) in fonts.layout(&text, Scale::uniform(size), point(pos.x, pos.y)).into_iter().enumerate() {
+            let char = chars.next().unwrap();
+            // Glyphs are cached at a few offsets within a pixel, the closest one is used
+            let position = glyph.position();
+            let (x, y) = ((position.x * steps).round() as i32, (position.y * steps).round() as i32);
+            let origin = (x.div_euclid(SUBPIXEL_STEPS as i32), y.div_euclid(SUBPIXEL_STEPS as i32));
+            let subpixel = (x.rem_euclid(SUBPIXEL_STEPS as i32) as u8, y.rem_euclid(SUBPIXEL_STEPS as i32) as u8);
+            let Some(cached) = cache.get(font, glyph.id(), size, subpixel) else { continue };
+
+            let rect = Rect::new(origin.0 + cached.left, origin.1 + cached.top, cached.width, cached.height);
+            touched = Some(touched.map_or(rect, |t| t.union(&rect)));
+            let paint = colorf(index as u32, char);
+            let visible = rect.intersection(&clip);
+            for ny in visible.y..visible.bottom() {
+                for nx in visible.x..visible.right() {
+                    let v = cached.coverage[((ny - rect.y) * rect.width + nx - rect.x) as usize];
+                    let pixel_index = (ny as usize * self.width as usize + nx as usize) * 4;
+                    // Mixing premultiplied colours keeps a transparent background from darkening the edges
+                    let local = inverse.apply(Vector2::new(nx as f32 + 0.5, ny as f32 + 0.5));
+                    let color = paint.sample(local).lerp(bg.sample(local), v);
+                    self.blend(pixel_index, color);
+                }
+            }
+        }
+        self.glyph_cache = cache;
+
+        if let Some(rect) = touched {
+            self.damage(rect.intersection(&clip));
+        }
+    }
+
+    fn draw_text_outlines<F: Fn(u32, char) -> Paint>(&mut self, text: String, pos: Vector2, size: f32, fonts: &FontSet, colorf: F, bg: Paint) {
+        let Some(inverse) = self.transform.inverse() else { return };
+        let transform = self.transform;
+        let tolerance = self.local_tolerance();
+        let clip = self.clip();
+        let mut chars = text.chars();
+
+        for (index, (_, glyph)) in fonts.layout(&text, Scale::uniform(size), point(pos.x, pos.y)).into_iter().enumerate() {
+            let char = chars.next().unwrap();
+            let mut outline = GlyphOutline {path: Path::new(), origin: glyph.position().into()};
+            if !glyph.unpositioned().build_outline(&mut outline) {
+                continue;
+            }
+            let polygons: Vec<Vec<Vector2>> = outline.path.flatten_with_tolerance(tolerance).into_iter()
+                .map(|l| l.points.into_iter().map(|p| transform.apply(p)).collect())
+                .collect();
+            let mask = rasterize(&polygons, FillRule::NonZero, clip);
+            let paint = colorf(index as u32, char);
+            let area = mask.area;
+            for y in area.y..area.bottom() {
+                for x in area.x..area.right() {
+                    let pixel_index = (y as usize * self.width as usize + x as usize) * 4;
+                    let local = inverse.apply(Vector2::new(x as f32 + 0.5, y as f32 + 0.5));
+                    self.blend(pixel_index, paint.sample(local).lerp(bg.sample(local), mask.get(x, y)));
+                }
+            }
+            self.damage(area);
+        }
+    }
+
+    /// Fills an axis aligned rect snapped to whole pixels, rotated or skewed rects are anti-aliased instead.
+    pub fn draw_rect(&mut self, paint: Paint, pos: Vector2, size: Vector2) {
+        if !self.transform.is_axis_aligned() {
+            let corners = vec![pos, Vector2::new(pos.x + size.x, pos.y), pos + size, Vector2::new(pos.x, pos.y + size.y)];
+            return self.fill_polygons(&paint, &[corners], FillRule::NonZero);
+        }
+        let inverse = self.transform.inverse().unwrap_or_default();
+        let (min, max) = self.transform.bounds(pos, pos + size);
+        let pos = Vector2::new(min.x.round(), min.y.round());
+        let size = Vector2::new((max.x - min.x).round(), (max.y - min.y).round());
+        let area = Rect::from_bounds(pos, pos + size).intersection(&self.clip());
+        for x in area.x..area.right() {
+            for y in area.y..area.bottom() {
+                let pixel_index = (y as usize * self.width as usize + x as usize) * 4;
+                self.blend(pixel_index, paint.sample(inverse.apply(Vector2::new(x as f32 + 0.5, y as f32 + 0.5))));
+            }
+        }
+
+        self.damage(area);
+    }
+
+    /// `thickness` reaches from the line to each edge, so the line is twice as wide, see [`Canvas::stroke_line`].
+    pub fn draw_line(&mut self, paint: Paint, a: Vector2, b: Vector2, thickness: f32) {
+        let margin = Vector2::new(thickness + 1.0, thickness + 1.0);
+        let min = Vector2::new(a.x.min(b.x), a.y.min(b.y)) - margin;
+        let max = Vector2::new(a.x.max(b.x), a.y.max(b.y)) + margin;
+        self.draw_sdf(paint, |v| dist_to_line(a, b, v) - thickness, min, max)
+    }
+
+    /// `thickness` reaches from the arc to each edge, like [`Canvas::draw_line`].
+    pub fn draw_arc(&mut self, paint: Paint, center: Vector2, radius: f32, start: f32, end: f32, thickness: f32) {
+        let margin = Vector2::new(thickness + radius + 1.0, thickness + radius + 1.0);
+        let min = center - margin;
+        let max = center + margin;
+        self.draw_sdf(paint, |v| dist_to_arc(center, radius, start, end, v) - thickness, min, max)
+    }
+
+    /// Fills a rect with anti-aliased rounded corners, then draws `border` inside its outline.
+    pub fn draw_rounded_rect<R: Into<CornerRadii>>(&mut self, paint: Paint, pos: Vector2, size: Vector2, radii: R, border: Option<Border>) {
+        let radii = radii.into();
+        let (corner, opposite) = (pos, pos + size);
+        let min = Vector2::new(corner.x.min(opposite.x), corner.y.min(opposite.y)) - Vector2::new(1.0, 1.0);
+        let max = Vector2::new(corner.x.max(opposite.x), corner.y.max(opposite.y)) + Vector2::new(1.0, 1.0);
+        self.draw_sdf(paint, |v| dist_to_rounded_rect(pos, size, radii, v), min, max);
+        if let Some(border) = border {
+            let half = border.width / 2.0;
+            self.draw_sdf(border.paint, |v| (dist_to_rounded_rect(pos, size, radii, v) + half).abs() - half, min, max);
+        }
+    }
+
+    pub fn fill_circle(&mut self, paint: Paint, center: Vector2, radius: f32) {
+        let margin = Vector2::new(radius + 1.0, radius + 1.0);
+        self.draw_sdf(paint, |v| dist_to_circle(center, radius, v), center - margin, center + margin)
+    }
+
+    /// Strokes the outline of a circle, `width` is centred on the outline.
+    pub fn stroke_circle(&mut self, paint: Paint, center: Vector2, radius: f32, width: f32) {
+        let margin = Vector2::new(radius + width / 2.0 + 1.0, radius + width / 2.0 + 1.0);
+        self.draw_sdf(paint, |v| dist_to_circle(center, radius, v).abs() - width / 2.0, center - margin, center + margin)
+    }
+
+    pub fn fill_ellipse(&mut self, paint: Paint, center: Vector2, radii: Vector2) {
+        let margin = radii + Vector2::new(1.0, 1.0);
+        self.draw_sdf(paint, |v| dist_to_ellipse(center, radii, v), center - margin, center + margin)
+    }
+
+    /// Strokes the outline of an ellipse, `width` is centred on the outline.
+    pub fn stroke_ellipse(&mut self, paint: Paint, center: Vector2, radii: Vector2, width: f32) {
+        let margin = radii + Vector2::new(width / 2.0 + 1.0, width / 2.0 + 1.0);
+        self.draw_sdf(paint, |v| dist_to_ellipse(center, radii, v).abs() - width / 2.0, center - margin, center + margin)
+    }
+
+    pub fn draw_where<F : Fn(Vector2) -> bool>(&mut self, paint: Paint, predicate: F, min: Vector2, max: Vector2) {
+        let Some(inverse) = self.transform.inverse() else { return };
+        let (min, max) = self.transform.bounds(min, max);
+        let area = Rect::from_bounds(Vector2::new(min.x.round(), min.y.round()), Vector2::new(max.x.round(), max.y.round())).intersection(&self.clip());
+        for x in area.x..area.right() {
+            for y in area.y..area.bottom() {
+                let pixel_index = (y as usize * self.width as usize + x as usize) * 4;
+                if predicate(inverse.apply(Vector2::new(x as f32, y as f32))) {
+                    self.blend(pixel_index, paint.sample(inverse.apply(Vector2::new(x as f32 + 0.5, y as f32 + 0.5))));
+                }
+            }
+        }
+
+        self.damage(area);
+    }
+
+    /// Anti-aliased counterpart of [`Canvas::draw_where`].
+    ///
+    /// `dist` is the signed distance from a point to the shape's edge, negative inside.
+    /// It is sampled at pixel centres and pixels within half a pixel of the edge are
+    /// blended by their coverage. `dist`, `min` and `max` are in current coordinates.
+    pub fn draw_sdf<F : Fn(Vector2) -> f32>(&mut self, paint: Paint, dist: F, min: Vector2, max: Vector2) {
+        let Some(inverse) = self.transform.inverse() else { return };
+        let scale = self.transform.scale_factor();
+        let (min, max) = self.transform.bounds(min, max);
+        let area = Rect::from_bounds(min, max).intersection(&self.clip());
+        for x in area.x..area.right() {
+            for y in area.y..area.bottom() {
+                let pixel_index = (y as usize * self.width as usize + x as usize) * 4;
+                let local = inverse.apply(Vector2::new(x as f32 + 0.5, y as f32 + 0.5));
+                let coverage = (0.5 - dist(local) * scale).clamp(0.0, 1.0);
+                if coverage > 0.0 {
+                    self.blend(pixel_index, paint.sample(local).lerp(Color::TRANSPARENT, coverage));
+                }
+            }
+        }
+
+        self.damage(area);
+    }
+}
+
+/// Collects a glyph outline into a [`Path`], offset to the glyph's position.
+struct GlyphOutline {
+    path: Path,
+    origin: Vector2,
+}
+
+impl OutlineBuilder for GlyphOutline {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let p = self.origin + Vector2::new(x, y);
+        self.path = std::mem::take(&mut self.path).move_to(p);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let p = self.origin + Vector2::new(x, y);
+        self.path = std::mem::take(&mut self.path).line_to(p);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (c, p) = (self.origin + Vector2::new(x1, y1), self.origin + Vector2::new(x, y));
+        self.path = std::mem::take(&mut self.path).quad_to(c, p);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (c1, c2, p) = (self.origin + Vector2::new(x1, y1), self.origin + Vector2::new(x2, y2), self.origin + Vector2::new(x, y));
+        self.path = std::mem::take(&mut self.path).cubic_to(c1, c2, p);
+    }
+
+    fn close(&mut self) {
+        self.path = std::mem::take(&mut self.path).close();
+    }
+}
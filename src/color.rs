@@ -0,0 +1,185 @@
+/// A colour with straight alpha, 8 bits per channel.
+///
+/// Canvases store premultiplied colours, the conversion happens when drawing.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Color {
+    pub(crate) r: u8,
+    pub(crate) g: u8,
+    pub(crate) b: u8,
+    pub(crate) a: u8,
+}
+
+/// Reads `0xAARRGGBB`, the same notation as `wl_shm` formats.
+impl From<u32> for Color {
+    fn from(value: u32) -> Self {
+        let [a, r, g, b] = value.to_be_bytes();
+        Self {r, g, b, a}
+    }
+}
+
+impl From<Color> for u32 {
+    fn from(value: Color) -> Self {
+        u32::from_be_bytes([value.a, value.r, value.g, value.b])
+    }
+}
+
+impl Color {
+    pub const TRANSPARENT: Color = Color {r: 0, g: 0, b: 0, a: 0};
+
+    pub fn new(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self {r, g, b, a}
+    }
+
+    pub fn r(&self) -> u8 {
+        self.r
+    }
+
+    pub fn g(&self) -> u8 {
+        self.g
+    }
+
+    pub fn b(&self) -> u8 {
+        self.b
+    }
+
+    pub fn a(&self) -> u8 {
+        self.a
+    }
+
+    pub fn lerp(&self, bg: Self, value: f32) -> Self {
+        Self {
+            r: (value * self.r as f32 + (1.0 - value) * bg.r as f32).round() as u8,
+            g: (value * self.g as f32 + (1.0 - value) * bg.g as f32).round() as u8,
+            b: (value * self.b as f32 + (1.0 - value) * bg.b as f32).round() as u8,
+            a: (value * self.a as f32 + (1.0 - value) * bg.a as f32).round() as u8,
+        }
+    }
+
+    /// Scales the colour channels by alpha, the representation Wayland expects.
+    pub fn premultiply(&self) -> Self {
+        Self {r: mul_div_255(self.r, self.a), g: mul_div_255(self.g, self.a), b: mul_div_255(self.b, self.a), a: self.a}
+    }
+
+    /// Inverse of [`Color::premultiply`], fully transparent colours come out as transparent black.
+    pub fn unpremultiply(&self) -> Self {
+        if self.a == 0 {
+            return Self::TRANSPARENT;
+        }
+        let un = |c: u8| ((c as u32 * 255 + self.a as u32 / 2) / self.a as u32).min(255) as u8;
+        Self {r: un(self.r), g: un(self.g), b: un(self.b), a: self.a}
+    }
+}
+
+/// `a * b / 255`, rounded.
+pub(crate) fn mul_div_255(a: u8, b: u8) -> u8 {
+    let t = a as u32 * b as u32 + 128;
+    ((t + (t >> 8)) >> 8) as u8
+}
+
+/// How drawn pixels are combined with the ones already in a canvas.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Draws over the destination, letting it show through translucent parts
+    #[default]
+    SourceOver,
+    /// Replaces the destination
+    Source,
+    /// Multiplies the colours, darkening the destination
+    Multiply,
+    /// Inverse of multiplying the inverted colours, lightening the destination
+    Screen,
+    /// Adds the colours, for glows
+    Plus,
+}
+
+impl BlendMode {
+    /// Combines two premultiplied colours.
+    pub fn apply(&self, src: Color, dst: Color) -> Color {
+        match self {
+            Self::SourceOver => {
+                let inv = 255 - src.a;
+                Color {
+                    r: src.r.saturating_add(mul_div_255(dst.r, inv)),
+                    g: src.g.saturating_add(mul_div_255(dst.g, inv)),
+                    b: src.b.saturating_add(mul_div_255(dst.b, inv)),
+                    a: src.a.saturating_add(mul_div_255(dst.a, inv)),
+                }
+            },
+            Self::Source => src,
+            Self::Multiply => {
+                let (inv_src, inv_dst) = (255 - src.a, 255 - dst.a);
+                let channel = |s: u8, d: u8| (mul_div_255(s, inv_dst) as u32 + mul_div_255(d, inv_src) as u32 + mul_div_255(s, d) as u32).min(255) as u8;
+                Color {r: channel(src.r, dst.r), g: channel(src.g, dst.g), b: channel(src.b, dst.b), a: src.a.saturating_add(mul_div_255(dst.a, inv_src))}
+            },
+            Self::Screen => {
+                let channel = |s: u8, d: u8| s.saturating_add(d - mul_div_255(s, d));
+                Color {r: channel(src.r, dst.r), g: channel(src.g, dst.g), b: channel(src.b, dst.b), a: channel(src.a, dst.a)}
+            },
+            Self::Plus => Color {r: src.r.saturating_add(dst.r), g: src.g.saturating_add(dst.g), b: src.b.saturating_add(dst.b), a: src.a.saturating_add(dst.a)},
+        }
+    }
+}
+
+/// Memory layout of a pixel, named like the `wl_shm` formats.
+///
+/// All of them are little-endian 32 bit words, so e.g. `Argb8888` is stored as B, G, R, A.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PixelFormat {
+    Argb8888,
+    /// Like `Argb8888`, but the alpha byte is ignored and the surface is opaque
+    Xrgb8888,
+    Argb2101010,
+    /// Like `Argb2101010`, but the alpha bits are ignored and the surface is opaque
+    Xrgb2101010,
+}
+
+impl PixelFormat {
+    pub fn has_alpha(&self) -> bool {
+        matches!(self, Self::Argb8888 | Self::Argb2101010)
+    }
+
+    /// The 8 bit format a canvas keeps its pixels in, 10 bit formats are only converted to when presenting
+    /// so blending keeps 8 bits of alpha.
+    pub fn storage(&self) -> PixelFormat {
+        match self {
+            Self::Argb8888 | Self::Argb2101010 => Self::Argb8888,
+            Self::Xrgb8888 | Self::Xrgb2101010 => Self::Xrgb8888,
+        }
+    }
+
+    pub fn encode(&self, color: Color) -> [u8; 4] {
+        let word = match self {
+            Self::Argb8888 => u32::from(color),
+            Self::Xrgb8888 => u32::from(color) | 0xFF000000,
+            Self::Argb2101010 | Self::Xrgb2101010 => {
+                let a = if self.has_alpha() { (color.a as u32 * 3 + 127) / 255 } else { 3 };
+                // Channels may not exceed the rounded alpha or the colour isn't valid premultiplied
+                let channel = |v: u8| to_10bit(v).min(a * 341);
+                a << 30 | channel(color.r) << 20 | channel(color.g) << 10 | channel(color.b)
+            },
+        };
+        word.to_le_bytes()
+    }
+
+    pub fn decode(&self, bytes: [u8; 4]) -> Color {
+        let word = u32::from_le_bytes(bytes);
+        match self {
+            Self::Argb8888 => word.into(),
+            Self::Xrgb8888 => (word | 0xFF000000).into(),
+            Self::Argb2101010 | Self::Xrgb2101010 => Color {
+                r: from_10bit(word >> 20),
+                g: from_10bit(word >> 10),
+                b: from_10bit(word),
+                a: if self.has_alpha() { ((word >> 30) * 255 / 3) as u8 } else { 0xFF },
+            },
+        }
+    }
+}
+
+fn to_10bit(v: u8) -> u32 {
+    (v as u32 * 1023 + 127) / 255
+}
+
+fn from_10bit(v: u32) -> u8 {
+    (((v & 0x3FF) * 255 + 511) / 1023) as u8
+}
@@ -0,0 +1,48 @@
+use crate::pixel_util::Rect;
+
+/// Past this many separate regions the compositor is better off with a single bounding box.
+const MAX_RECTS: usize = 8;
+
+/// Regions of a buffer changed since the last commit.
+#[derive(Default)]
+pub struct Damage {
+    rects: Vec<Rect>,
+}
+
+impl Damage {
+    pub fn new() -> Self {
+        Self {rects: vec![]}
+    }
+
+    /// Adds a changed region, merging it with every region it touches.
+    pub fn add(&mut self, rect: Rect) {
+        if rect.is_empty() {
+            return;
+        }
+
+        let mut rect = rect;
+        // Merging can make the rect reach regions it didn't touch before, so keep going until nothing touches it
+        while let Some(index) = self.rects.iter().position(|r| r.touches(&rect)) {
+            rect = rect.union(&self.rects.swap_remove(index));
+        }
+        self.rects.push(rect);
+
+        if self.rects.len() > MAX_RECTS {
+            let bounds = self.rects.iter().fold(rect, |acc, r| acc.union(r));
+            self.rects = vec![bounds];
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rects.is_empty()
+    }
+
+    pub fn rects(&self) -> &[Rect] {
+        &self.rects
+    }
+
+    /// Returns the accumulated regions and starts tracking from scratch.
+    pub fn take(&mut self) -> Vec<Rect> {
+        std::mem::take(&mut self.rects)
+    }
+}
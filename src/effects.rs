@@ -0,0 +1,157 @@
+use crate::{canvas::Canvas, color::Color, path::{FillRule, Path}, pixel_util::{Rect, Vector2}, raster::{rasterize, Mask}};
+
+/// Largest standard deviation blurred, wider ones are indistinguishable from a flat fill on any surface.
+const MAX_SIGMA: f32 = 4096.0;
+
+/// Radii of three box blurs that together approximate a Gaussian blur with `sigma`.
+fn gaussian_boxes(sigma: f32) -> [usize; 3] {
+    if sigma.is_nan() || sigma <= 0.0 {
+        return [0; 3];
+    }
+    let sigma = sigma.min(MAX_SIGMA);
+    let ideal = (4.0 * sigma * sigma + 1.0).sqrt();
+    let mut lower = ideal.floor() as i32;
+    if lower % 2 == 0 {
+        lower -= 1;
+    }
+    let upper = lower + 2;
+    // How many of the passes use the narrower box
+    let m = ((12.0 * sigma * sigma - (3 * lower * lower + 12 * lower + 9) as f32) / (-4 * lower - 4) as f32).round() as i32;
+    let mut radii = [0; 3];
+    for (i, r) in radii.iter_mut().enumerate() {
+        let width = if (i as i32) < m { lower } else { upper };
+        *r = (width.max(1) as usize - 1) / 2;
+    }
+    radii
+}
+
+/// One horizontal and one vertical box blur pass over `channels` interleaved planes,
+/// samples past the edges repeat the edge pixels. The radius is clamped to the length of each line.
+fn box_blur_plane(data: &mut [f32], width: usize, height: usize, channels: usize, radius: usize) {
+    if radius == 0 || width == 0 || height == 0 {
+        return;
+    }
+    let mut line = vec![];
+    let mut pass = |data: &mut [f32], len: usize, count: usize, stride: usize, step: usize| {
+        let radius = radius.min(len);
+        let scale = 1.0 / (2 * radius + 1) as f32;
+        for i in 0..count {
+            for c in 0..channels {
+                let at = |k: usize| i * stride + k * step + c;
+                line.clear();
+                line.extend((0..len).map(|k| data[at(k)]));
+                let get = |k: isize| line[k.clamp(0, len as isize - 1) as usize];
+                let mut sum: f32 = (-(radius as isize)..=radius as isize).map(get).sum();
+                for k in 0..len as isize {
+                    data[at(k as usize)] = sum * scale;
+                    sum += get(k + radius as isize + 1) - get(k - radius as isize);
+                }
+            }
+        }
+    };
+    pass(data, width, height, width * channels, channels);
+    pass(data, height, width, channels, width * channels);
+}
+
+impl Canvas {
+    /// Gaussian blur of the pixels in `rect`, with a standard deviation of half the `radius` like CSS.
+    ///
+    /// Pixels around `rect` are blurred into it, only pixels within it and the clip change.
+    /// `rect` is in canvas pixels and ignores the transform.
+    pub fn blur(&mut self, rect: Rect, radius: f32) {
+        self.blur_passes(rect, &gaussian_boxes(radius / 2.0));
+    }
+
+    /// Averages every pixel in `rect` with its neighbours up to `radius` pixels away, faster than [`Canvas::blur`].
+    pub fn box_blur(&mut self, rect: Rect, radius: u32) {
+        self.blur_passes(rect, &[radius as usize]);
+    }
+
+    fn blur_passes(&mut self, rect: Rect, radii: &[usize]) {
+        let target = rect.intersection(&self.clip());
+        if target.is_empty() {
+            return;
+        }
+        // Nothing beyond the canvas is read, so larger radii don't reach further
+        let limit = self.width().max(self.height()) as usize;
+        let reach = radii.iter().map(|&r| r.min(limit)).sum::<usize>() as i32;
+        let canvas = Rect::new(0, 0, self.width() as i32, self.height() as i32);
+        let area = Rect::new(target.x - reach, target.y - reach, target.width + 2 * reach, target.height + 2 * reach).intersection(&canvas);
+
+        let (w, h) = (area.width as usize, area.height as usize);
+        let stride = self.width() as usize;
+        let index = |x: i32, y: i32| (y as usize * stride + x as usize) * 4;
+        let mut plane = Vec::with_capacity(w * h * 4);
+        for y in area.y..area.bottom() {
+            for x in area.x..area.right() {
+                let p = &self.data()[index(x, y)..index(x, y) + 4];
+                let c = self.buffer_format().decode([p[0], p[1], p[2], p[3]]);
+                plane.extend_from_slice(&[c.r as f32, c.g as f32, c.b as f32, c.a as f32]);
+            }
+        }
+        for &radius in radii {
+            box_blur_plane(&mut plane, w, h, 4, radius);
+        }
+
+        let format = self.buffer_format();
+        for y in target.y..target.bottom() {
+            for x in target.x..target.right() {
+                let i = ((y - area.y) as usize * w + (x - area.x) as usize) * 4;
+                let v = |k: usize| plane[i + k].round().clamp(0.0, 255.0) as u8;
+                let a = v(3);
+                // Rounding may leave a colour channel above alpha, which isn't valid premultiplied
+                let color = Color::new(v(0).min(a), v(1).min(a), v(2).min(a), a);
+                let at = index(x, y);
+                self.data_mut()[at..at + 4].copy_from_slice(&format.encode(color));
+            }
+        }
+        self.damage(target);
+    }
+
+    /// Draws the blurred silhouette of `shape` moved by `offset`, like a CSS box shadow.
+    ///
+    /// Draw the shape itself afterwards, the shadow is not cut out beneath it.
+    pub fn draw_shadow(&mut self, shape: &Path, offset: Vector2, radius: f32, color: Color) {
+        let transform = self.current_transform();
+        let scale = transform.scale_factor();
+        let clip = self.clip();
+        // The mask grows with the reach, so it is kept within the size of the clip
+        let limit = clip.width.max(clip.height).max(0) as usize / 3;
+        let radii = gaussian_boxes(radius * scale / 2.0).map(|r| r.min(limit));
+        let reach = radii.iter().sum::<usize>() as f32;
+
+        let polygons: Vec<Vec<Vector2>> = shape.flatten_with_tolerance(self.local_tolerance()).into_iter()
+            .map(|l| l.points.into_iter().map(|p| transform.apply(p + offset)).collect())
+            .collect();
+        if polygons.is_empty() {
+            return;
+        }
+        // Parts of the shape just outside the clip still cast a shadow into it
+        let pad = reach as i32 + 1;
+        let padded_clip = Rect::new(clip.x - pad, clip.y - pad, clip.width + 2 * pad, clip.height + 2 * pad);
+        let shape = rasterize(&polygons, FillRule::NonZero, padded_clip);
+        let area = Rect::new(shape.area.x - pad, shape.area.y - pad, shape.area.width + 2 * pad, shape.area.height + 2 * pad).intersection(&padded_clip);
+        let mut mask = Mask {area, coverage: vec![0.0; (area.width * area.height) as usize]};
+        for y in shape.area.y..shape.area.bottom() {
+            for x in shape.area.x..shape.area.right() {
+                mask.coverage[((y - area.y) * area.width + x - area.x) as usize] = shape.get(x, y);
+            }
+        }
+        for radius in radii {
+            box_blur_plane(&mut mask.coverage, area.width as usize, area.height as usize, 1, radius);
+        }
+
+        let color = color.premultiply();
+        let target = area.intersection(&clip);
+        for y in target.y..target.bottom() {
+            for x in target.x..target.right() {
+                let coverage = mask.get(x, y).clamp(0.0, 1.0);
+                if coverage > 0.0 {
+                    let pixel_index = (y as usize * self.width() as usize + x as usize) * 4;
+                    self.blend(pixel_index, color.lerp(Color::TRANSPARENT, coverage));
+                }
+            }
+        }
+        self.damage(target);
+    }
+}
@@ -0,0 +1,42 @@
+use std::{fs::File, io::{BufWriter, Write}, path::Path};
+
+use crate::{canvas::Canvas, widget::WidgetError};
+
+impl Canvas {
+    /// The pixels as straight RGBA bytes, converted from the canvas' premultiplied pixel format.
+    pub fn to_rgba(&self) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity(self.data().len());
+        for p in self.data().chunks_exact(4) {
+            let color = self.buffer_format().decode([p[0], p[1], p[2], p[3]]).unpremultiply();
+            rgba.extend_from_slice(&[color.r, color.g, color.b, color.a]);
+        }
+        rgba
+    }
+
+    pub fn write_png<W: Write>(&self, w: W) -> Result<(), WidgetError> {
+        let mut encoder = png::Encoder::new(w, self.width(), self.height());
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.to_rgba())?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    /// Writes a binary (P6) PPM, which has no alpha channel so it is dropped.
+    pub fn write_ppm<W: Write>(&self, mut w: W) -> Result<(), WidgetError> {
+        write!(w, "P6\n{} {}\n255\n", self.width(), self.height())?;
+        let rgb: Vec<u8> = self.to_rgba().chunks_exact(4).flat_map(|p| [p[0], p[1], p[2]]).collect();
+        w.write_all(&rgb)?;
+        w.flush()?;
+        Ok(())
+    }
+
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> Result<(), WidgetError> {
+        self.write_png(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn save_ppm<P: AsRef<Path>>(&self, path: P) -> Result<(), WidgetError> {
+        self.write_ppm(BufWriter::new(File::create(path)?))
+    }
+}
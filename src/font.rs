@@ -0,0 +1,363 @@
+use std::{collections::{HashMap, HashSet}, env, fs::{self, File}, io::{self, Read, Seek, SeekFrom}, path::{Path, PathBuf}, sync::{Mutex, OnceLock}};
+
+use rusttype::{point, Font, GlyphId, Point, PositionedGlyph, Scale, VMetrics};
+use ttf_parser::{name_id, Style, Width};
+
+use crate::widget::WidgetError;
+
+/// Fonts tried in order for every character, so text mixing scripts and icons can be drawn.
+///
+/// Characters no font has a glyph for use the first font's missing glyph.
+/// Line metrics always come from the first font.
+#[derive(Clone)]
+pub struct FontSet<'a> {
+    fonts: Vec<Font<'a>>,
+}
+
+impl<'a> FontSet<'a> {
+    pub fn new(primary: Font<'a>) -> Self {
+        Self {fonts: vec![primary]}
+    }
+
+    /// Adds a font tried after all the previous ones.
+    pub fn fallback(mut self, font: Font<'a>) -> Self {
+        self.fonts.push(font);
+        self
+    }
+
+    pub fn fonts(&self) -> &[Font<'a>] {
+        &self.fonts
+    }
+
+    /// The first font with a glyph for `c`.
+    pub fn font_for(&self, c: char) -> &Font<'a> {
+        self.fonts.iter().find(|f| f.glyph(c).id() != GlyphId(0)).unwrap_or(&self.fonts[0])
+    }
+
+    pub fn v_metrics(&self, scale: Scale) -> VMetrics {
+        self.fonts[0].v_metrics(scale)
+    }
+
+    /// Positions one glyph per character along a line starting at `start`, like [`Font::layout`].
+    /// Pairs of characters from the same font are kerned.
+    pub fn layout(&self, text: &str, scale: Scale, start: Point<f32>) -> Vec<(&Font<'a>, PositionedGlyph<'a>)> {
+        let mut caret = start.x;
+        let mut last: Option<(&Font<'a>, GlyphId)> = None;
+        text.chars().map(|c| {
+            let font = self.font_for(c);
+            let glyph = font.glyph(c).scaled(scale);
+            if let Some((prev_font, prev)) = last.filter(|(f, _)| std::ptr::eq(*f, font)) {
+                caret += prev_font.pair_kerning(scale, prev, glyph.id());
+            }
+            last = Some((font, glyph.id()));
+            let advance = glyph.h_metrics().advance_width;
+            let glyph = glyph.positioned(point(caret, start.y));
+            caret += advance;
+            (font, glyph)
+        }).collect()
+    }
+}
+
+impl<'a> From<Font<'a>> for FontSet<'a> {
+    fn from(value: Font<'a>) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<'a> From<&Font<'a>> for FontSet<'a> {
+    fn from(value: &Font<'a>) -> Self {
+        Self::new(value.clone())
+    }
+}
+
+impl<'a> From<&FontSet<'a>> for FontSet<'a> {
+    fn from(value: &FontSet<'a>) -> Self {
+        value.clone()
+    }
+}
+
+/// Weight and slant requested by a [`FontManager`] query, parsed from fontconfig's pattern syntax.
+///
+/// `"JetBrains Mono,monospace:weight=medium:slant=italic"` asks for the first family that is installed,
+/// `:bold` and `:italic` work as shorthands. Numeric weights are OpenType weights from 100 to 900.
+/// Other properties, like sizes, are ignored.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FontQuery {
+    pub families: Vec<String>,
+    pub weight: u16,
+    pub italic: bool,
+}
+
+impl FontQuery {
+    pub fn parse(query: &str) -> Result<Self, WidgetError> {
+        let invalid = || WidgetError::InvalidFontQuery(query.to_string());
+        let mut parts = query.split(':');
+        let families: Vec<String> = parts.next().unwrap_or_default().split(',')
+            .map(|f| f.trim().to_string()).filter(|f| !f.is_empty()).collect();
+        if families.is_empty() {
+            return Err(invalid());
+        }
+
+        let mut result = Self {families, weight: 400, italic: false};
+        for part in parts {
+            let (key, value) = part.split_once('=').map_or((None, part), |(k, v)| (Some(k.trim()), v));
+            let value = value.trim().to_ascii_lowercase();
+            match (key, value.as_str()) {
+                (Some("slant") | None, "italic" | "oblique") => result.italic = true,
+                (Some("slant") | None, "roman") => result.italic = false,
+                (Some("slant"), _) => return Err(invalid()),
+                (Some("weight") | None, name) => match weight_from_name(name) {
+                    Some(weight) => result.weight = weight,
+                    None if key.is_some() => return Err(invalid()),
+                    None => {},
+                },
+                _ => {},
+            }
+        }
+        Ok(result)
+    }
+}
+
+fn weight_from_name(name: &str) -> Option<u16> {
+    Some(match name {
+        "thin" => 100,
+        "extralight" | "ultralight" => 200,
+        "light" => 300,
+        "demilight" | "semilight" => 350,
+        "book" => 380,
+        "regular" | "normal" => 400,
+        "medium" => 500,
+        "demibold" | "semibold" => 600,
+        "bold" => 700,
+        "extrabold" | "ultrabold" => 800,
+        "black" | "heavy" => 900,
+        "extrablack" | "ultrablack" => 950,
+        number => number.parse().ok().filter(|w| (1..=1000).contains(w))?,
+    })
+}
+
+/// A face found while scanning, only its names and style are kept until it is loaded.
+struct FaceInfo {
+    path: PathBuf,
+    index: u32,
+    families: Vec<String>,
+    weight: u16,
+    width: Width,
+    italic: bool,
+}
+
+/// Installed fonts, found by family name and loaded once no matter how often they are asked for.
+///
+/// Fonts returned for the same face share their glyphs in each canvas' cache. Font files are read once
+/// and kept for the rest of the process, so every face of a collection borrows the same bytes.
+pub struct FontManager {
+    faces: Vec<FaceInfo>,
+    aliases: HashMap<String, Vec<String>>,
+    loaded: Mutex<HashMap<(PathBuf, u32), Font<'static>>>,
+}
+
+impl FontManager {
+    /// Scans the directories listed by fontconfig's configuration and the XDG font directories.
+    pub fn new() -> Self {
+        let mut config = fontconfig_parser::FontConfig::default();
+        let config_file = env::var_os("FONTCONFIG_FILE").map_or_else(|| PathBuf::from("/etc/fonts/fonts.conf"), PathBuf::from);
+        // Without a readable configuration the standard directories are still scanned
+        let _ = config.merge_config(&config_file);
+
+        let home = env::var_os("HOME").map(PathBuf::from);
+        let data_home = env::var_os("XDG_DATA_HOME").map(PathBuf::from).or_else(|| home.as_ref().map(|h| h.join(".local/share")));
+        let data_dirs = env::var("XDG_DATA_DIRS").ok().filter(|d| !d.is_empty()).unwrap_or_else(|| "/usr/local/share:/usr/share".into());
+
+        let mut dirs: Vec<PathBuf> = config.dirs.into_iter().map(|d| d.path).collect();
+        dirs.extend(data_home.into_iter().chain(env::split_paths(&data_dirs)).map(|d| d.join("fonts")));
+        dirs.extend(home.map(|h| h.join(".fonts")));
+
+        let mut aliases: HashMap<String, Vec<String>> = HashMap::new();
+        for alias in config.aliases {
+            let families = aliases.entry(alias.alias.to_lowercase()).or_default();
+            families.extend(alias.prefer.into_iter().chain(alias.accept).chain(alias.default));
+        }
+        Self {aliases, ..Self::from_dirs(dirs)}
+    }
+
+    /// Scans only `dirs` and their subdirectories, without fontconfig's aliases.
+    pub fn from_dirs<I: IntoIterator<Item = P>, P: AsRef<Path>>(dirs: I) -> Self {
+        let mut faces = vec![];
+        let mut seen = HashSet::new();
+        for dir in dirs {
+            scan_dir(dir.as_ref(), &mut seen, &mut faces);
+        }
+        Self {faces, aliases: HashMap::new(), loaded: Mutex::new(HashMap::new())}
+    }
+
+    /// The manager shared by every widget, scanned the first time it is used.
+    pub fn system() -> &'static FontManager {
+        static SYSTEM: OnceLock<FontManager> = OnceLock::new();
+        SYSTEM.get_or_init(FontManager::new)
+    }
+
+    /// Names of every installed family, sorted.
+    pub fn families(&self) -> Vec<&str> {
+        let mut families: Vec<&str> = self.faces.iter().flat_map(|f| f.families.iter().map(String::as_str)).collect();
+        families.sort_unstable();
+        families.dedup();
+        families
+    }
+
+    /// The closest match for the first family of `query` that is installed.
+    pub fn font(&self, query: &str) -> Result<Font<'static>, WidgetError> {
+        let parsed = FontQuery::parse(query)?;
+        parsed.families.iter().find_map(|family| self.find(family, &parsed))
+            .ok_or_else(|| WidgetError::FontNotFound(query.to_string()))
+            .and_then(|face| self.load(face))
+    }
+
+    /// Every installed family of `query` in order, so later families cover characters the first lacks.
+    pub fn font_set(&self, query: &str) -> Result<FontSet<'static>, WidgetError> {
+        let parsed = FontQuery::parse(query)?;
+        let mut fonts = vec![];
+        for face in parsed.families.iter().filter_map(|family| self.find(family, &parsed)) {
+            fonts.push(self.load(face)?);
+        }
+        let mut fonts = fonts.into_iter();
+        let primary = fonts.next().ok_or_else(|| WidgetError::FontNotFound(query.to_string()))?;
+        Ok(fonts.fold(FontSet::new(primary), FontSet::fallback))
+    }
+
+    /// The face of `family` closest to the query's style, trying fontconfig's substitutes for generic names like `monospace`.
+    fn find(&self, family: &str, query: &FontQuery) -> Option<&FaceInfo> {
+        let mut visited = HashSet::new();
+        let mut pending = vec![family.to_lowercase()];
+        while let Some(family) = pending.pop() {
+            if !visited.insert(family.clone()) {
+                continue;
+            }
+            let best = self.faces.iter()
+                .filter(|f| f.families.iter().any(|name| name.to_lowercase() == family))
+                .min_by_key(|f| (f.italic != query.italic, f.width.to_number().abs_diff(Width::Normal.to_number()), f.weight.abs_diff(query.weight), f.weight < query.weight));
+            if best.is_some() {
+                return best;
+            }
+            if let Some(substitutes) = self.aliases.get(&family) {
+                pending.extend(substitutes.iter().rev().map(|s| s.to_lowercase()));
+            }
+        }
+        None
+    }
+
+    fn load(&self, face: &FaceInfo) -> Result<Font<'static>, WidgetError> {
+        let mut loaded = self.loaded.lock().unwrap_or_else(|e| e.into_inner());
+        let key = (face.path.clone(), face.index);
+        if let Some(font) = loaded.get(&key) {
+            return Ok(font.clone());
+        }
+        let font = Font::try_from_bytes_and_index(file_data(&face.path)?, face.index).ok_or(WidgetError::InvalidFont)?;
+        loaded.insert(key, font.clone());
+        Ok(font)
+    }
+}
+
+impl Default for FontManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The contents of a font file, read the first time any manager loads a face from it.
+fn file_data(path: &Path) -> io::Result<&'static [u8]> {
+    static FILES: OnceLock<Mutex<HashMap<PathBuf, &'static [u8]>>> = OnceLock::new();
+    let mut files = FILES.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(data) = files.get(path) {
+        return Ok(data);
+    }
+    let data: &'static [u8] = Box::leak(fs::read(path)?.into_boxed_slice());
+    files.insert(path.to_path_buf(), data);
+    Ok(data)
+}
+
+fn scan_dir(dir: &Path, seen: &mut HashSet<PathBuf>, faces: &mut Vec<FaceInfo>) {
+    // Directories listed twice or reached through symlinks are only scanned once
+    let Ok(canonical) = dir.canonicalize() else { return };
+    if !seen.insert(canonical) {
+        return;
+    }
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    let mut paths: Vec<PathBuf> = entries.filter_map(|e| e.ok().map(|e| e.path())).collect();
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            scan_dir(&path, seen, faces);
+            continue;
+        }
+        let is_font = path.extension().and_then(|e| e.to_str())
+            .is_some_and(|e| ["ttf", "otf", "ttc", "otc"].contains(&e.to_ascii_lowercase().as_str()));
+        if !is_font || !seen.insert(path.clone()) {
+            continue;
+        }
+        // Unreadable or malformed files are skipped
+        if let Ok(found) = read_faces(&path) {
+            faces.extend(found);
+        }
+    }
+}
+
+/// Reads the faces of a font file from its table directories and `name` and `OS/2` tables,
+/// the rest of the file, mostly glyph data, is only read once the font is loaded.
+fn read_faces(path: &Path) -> io::Result<Vec<FaceInfo>> {
+    let mut file = File::open(path)?;
+    let file_len = file.metadata()?.len();
+    let mut read_at = |offset: u64, len: u64| -> io::Result<Vec<u8>> {
+        if offset.saturating_add(len) > file_len {
+            return Err(io::ErrorKind::InvalidData.into());
+        }
+        let mut data = vec![0; len as usize];
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut data)?;
+        Ok(data)
+    };
+    let u16_at = |data: &[u8], i: usize| u16::from_be_bytes([data[i], data[i + 1]]);
+    let u32_at = |data: &[u8], i: usize| u32::from_be_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+
+    let header = read_at(0, 12)?;
+    let offsets = if &header[..4] == b"ttcf" {
+        let count = u32_at(&header, 8) as u64;
+        let table = read_at(12, count * 4)?;
+        table.chunks_exact(4).map(|o| u32_at(o, 0)).collect()
+    } else {
+        vec![0]
+    };
+
+    let mut faces = vec![];
+    for (index, offset) in offsets.into_iter().enumerate() {
+        let count = u16_at(&read_at(offset as u64, 12)?, 4) as u64;
+        let records = read_at(offset as u64 + 12, count * 16)?;
+        let table = |tag: &[u8]| records.chunks_exact(16).find(|r| &r[..4] == tag).map(|r| (u32_at(r, 8) as u64, u32_at(r, 12) as u64));
+
+        let Some((name_offset, name_len)) = table(b"name") else { continue };
+        let name = read_at(name_offset, name_len)?;
+        let os2 = match table(b"OS/2") {
+            Some((offset, len)) => Some(read_at(offset, len)?),
+            None => None,
+        };
+        let Some(names) = ttf_parser::name::Table::parse(&name) else { continue };
+        let os2 = os2.as_deref().and_then(ttf_parser::os2::Table::parse);
+
+        let mut families: Vec<String> = names.names.into_iter()
+            .filter(|n| n.name_id == name_id::TYPOGRAPHIC_FAMILY || n.name_id == name_id::FAMILY)
+            .filter_map(|n| n.to_string())
+            .collect();
+        families.sort_unstable();
+        families.dedup();
+        if !families.is_empty() {
+            faces.push(FaceInfo {
+                path: path.to_path_buf(),
+                index: index as u32,
+                families,
+                weight: os2.map_or(400, |t| t.weight().to_number()),
+                width: os2.map_or(Width::Normal, |t| t.width()),
+                italic: os2.is_some_and(|t| t.style() != Style::Normal),
+            });
+        }
+    }
+    Ok(faces)
+}
@@ -0,0 +1,47 @@
+use std::ops::{Deref, DerefMut};
+
+use crate::{canvas::Canvas, widget::{WidgetComponents, WidgetError}};
+
+/// A batch of drawing operations on a widget.
+///
+/// Primitives come from the widget's [`Canvas`] and only touch its pixels, nothing
+/// reaches the compositor until [`Frame::present`] attaches the result and commits the surface once.
+/// Dropping a frame without presenting it keeps the pixels for the next frame.
+pub struct Frame<'a> {
+    comp: &'a mut WidgetComponents,
+}
+
+impl<'a> Frame<'a> {
+    pub(crate) fn new(comp: &'a mut WidgetComponents) -> Self {
+        Self {comp}
+    }
+
+    /// Asks for another frame once this one is on screen, for animations.
+    pub fn request_redraw(&self) {
+        self.comp.request_redraw();
+    }
+
+    /// Stops [`WidgetComponents::run`] once this frame is done.
+    pub fn close(&self) {
+        self.comp.close();
+    }
+
+    /// Attaches the frame to the surface and commits it.
+    pub fn present(self) -> Result<(), WidgetError> {
+        self.comp.present()
+    }
+}
+
+impl Deref for Frame<'_> {
+    type Target = Canvas;
+
+    fn deref(&self) -> &Self::Target {
+        &self.comp.canvas
+    }
+}
+
+impl DerefMut for Frame<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.comp.canvas
+    }
+}
@@ -0,0 +1,86 @@
+use std::{collections::HashMap, sync::Arc};
+
+use rusttype::{point, Font, GlyphId, Scale};
+
+/// Horizontal and vertical positions a glyph is rasterized at within a pixel.
+pub(crate) const SUBPIXEL_STEPS: u8 = 4;
+/// The cache is emptied once it holds this many glyphs.
+const MAX_GLYPHS: usize = 2048;
+
+/// Tells whether a font is still loaded, `None` for borrowed fonts which can't be tracked.
+type Liveness = Option<Box<dyn Fn() -> bool + Send + Sync>>;
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+struct GlyphKey {
+    font: usize,
+    glyph: GlyphId,
+    size: u32,
+    subpixel: (u8, u8),
+}
+
+/// Coverage of a rasterized glyph, offset from the whole pixel its origin falls in.
+pub(crate) struct CachedGlyph {
+    pub left: i32,
+    pub top: i32,
+    pub width: i32,
+    pub height: i32,
+    pub coverage: Vec<f32>,
+}
+
+/// Glyphs rasterized by [`Canvas::draw_text`](crate::canvas::Canvas::draw_text), so redrawing the same text only blits them.
+///
+/// Fonts are told apart by the address of their shared data, glyphs of owned fonts
+/// are dropped once every clone of the font is gone so a new font can't inherit them.
+#[derive(Default)]
+pub(crate) struct GlyphCache {
+    glyphs: HashMap<GlyphKey, Option<CachedGlyph>>,
+    fonts: Vec<(usize, Liveness)>,
+}
+
+impl GlyphCache {
+    fn font_key(&mut self, font: &Font) -> usize {
+        let (key, alive): (usize, Liveness) = match font {
+            Font::Ref(face) => (Arc::as_ptr(face) as *const u8 as usize, None),
+            Font::Owned(face) => {
+                let weak = Arc::downgrade(face);
+                (Arc::as_ptr(face) as *const u8 as usize, Some(Box::new(move || weak.strong_count() > 0)))
+            },
+        };
+        if !self.fonts.iter().any(|(k, _)| *k == key) {
+            // A new font may reuse the address of a dropped one
+            let dead: Vec<usize> = self.fonts.iter().filter(|(_, alive)| alive.as_ref().is_some_and(|f| !f())).map(|(k, _)| *k).collect();
+            if !dead.is_empty() {
+                self.fonts.retain(|(k, _)| !dead.contains(k));
+                self.glyphs.retain(|g, _| !dead.contains(&g.font));
+            }
+            self.fonts.push((key, alive));
+        }
+        key
+    }
+
+    /// The glyph at `size` shifted by `subpixel` steps, rasterizing it on a miss.
+    /// `None` for glyphs without an outline, like spaces.
+    pub fn get(&mut self, font: &Font, glyph: GlyphId, size: f32, subpixel: (u8, u8)) -> Option<&CachedGlyph> {
+        let key = GlyphKey {font: self.font_key(font), glyph, size: size.to_bits(), subpixel};
+        if self.glyphs.len() >= MAX_GLYPHS && !self.glyphs.contains_key(&key) {
+            self.glyphs.clear();
+        }
+        self.glyphs.entry(key).or_insert_with(|| {
+            let offset = point(subpixel.0 as f32 / SUBPIXEL_STEPS as f32, subpixel.1 as f32 / SUBPIXEL_STEPS as f32);
+            let glyph = font.glyph(glyph).scaled(Scale::uniform(size)).positioned(offset);
+            let bb = glyph.pixel_bounding_box()?;
+            let mut coverage = vec![0.0; (bb.width() * bb.height()) as usize];
+            glyph.draw(|x, y, v| coverage[(y * bb.width() as u32 + x) as usize] = v);
+            Some(CachedGlyph {left: bb.min.x, top: bb.min.y, width: bb.width(), height: bb.height(), coverage})
+        }).as_ref()
+    }
+
+    pub fn len(&self) -> usize {
+        self.glyphs.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.glyphs.clear();
+        self.fonts.clear();
+    }
+}
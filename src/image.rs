@@ -0,0 +1,159 @@
+use std::{fs::File, io::{BufReader, Read}, path::Path};
+
+use crate::{canvas::Canvas, color::Color, pixel_util::{dist_to_rounded_rect, CornerRadii, Rect, Vector2}, widget::WidgetError};
+
+/// How an image is sampled when it is drawn at another size.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Filter {
+    /// Picks the closest pixel, keeps pixel art sharp
+    Nearest,
+    /// Blends the four closest pixels
+    #[default]
+    Bilinear,
+}
+
+/// An RGBA image that can be drawn with [`Canvas::draw_image`].
+///
+/// Pixels are kept premultiplied like in a canvas, so filtering doesn't bleed
+/// the colour of transparent pixels into their neighbours.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<Color>,
+}
+
+impl Image {
+    /// Creates an image from straight RGBA bytes, row by row.
+    pub fn from_rgba(width: u32, height: u32, rgba: &[u8]) -> Result<Self, WidgetError> {
+        if rgba.len() != width as usize * height as usize * 4 {
+            return Err(WidgetError::InvalidImage);
+        }
+        let pixels = rgba.chunks_exact(4).map(|p| Color::new(p[0], p[1], p[2], p[3]).premultiply()).collect();
+        Ok(Self {width, height, pixels})
+    }
+
+    /// Decodes a PNG, palette, grayscale and 16 bit images are converted to 8 bit RGBA.
+    pub fn read_png<R: Read>(r: R) -> Result<Self, WidgetError> {
+        let mut decoder = png::Decoder::new(r);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer)?;
+        buffer.truncate(info.buffer_size());
+
+        let rgba: Vec<u8> = match info.color_type {
+            png::ColorType::Rgba => buffer,
+            png::ColorType::Rgb => buffer.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 0xFF]).collect(),
+            png::ColorType::GrayscaleAlpha => buffer.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+            png::ColorType::Grayscale => buffer.iter().flat_map(|&l| [l, l, l, 0xFF]).collect(),
+            png::ColorType::Indexed => return Err(WidgetError::InvalidImage),
+        };
+        Self::from_rgba(info.width, info.height, &rgba)
+    }
+
+    /// Decodes a baseline or progressive JPEG, which is always opaque.
+    pub fn read_jpeg<R: Read>(r: R) -> Result<Self, WidgetError> {
+        let mut decoder = jpeg_decoder::Decoder::new(r);
+        let buffer = decoder.decode()?;
+        let info = decoder.info().ok_or(WidgetError::InvalidImage)?;
+
+        let rgba: Vec<u8> = match info.pixel_format {
+            jpeg_decoder::PixelFormat::RGB24 => buffer.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 0xFF]).collect(),
+            jpeg_decoder::PixelFormat::L8 => buffer.iter().flat_map(|&l| [l, l, l, 0xFF]).collect(),
+            // Big endian 16 bit luminance, the high byte is enough
+            jpeg_decoder::PixelFormat::L16 => buffer.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], 0xFF]).collect(),
+            jpeg_decoder::PixelFormat::CMYK32 => buffer.chunks_exact(4).flat_map(|p| {
+                let k = 255 - p[3] as u32;
+                let channel = |c: u8| ((255 - c as u32) * k / 255) as u8;
+                [channel(p[0]), channel(p[1]), channel(p[2]), 0xFF]
+            }).collect(),
+        };
+        Self::from_rgba(info.width as u32, info.height as u32, &rgba)
+    }
+
+    /// Loads a PNG or JPEG file, telling them apart by their signature rather than the extension.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, WidgetError> {
+        let mut file = BufReader::new(File::open(path)?);
+        let mut signature = [0u8; 8];
+        file.read_exact(&mut signature)?;
+        let r = signature.chain(file);
+        if signature == [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'] {
+            Self::read_png(r)
+        } else if signature[..3] == [0xFF, 0xD8, 0xFF] {
+            Self::read_jpeg(r)
+        } else {
+            Err(WidgetError::InvalidImage)
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The colour at a pixel, with straight alpha.
+    pub fn pixel(&self, x: u32, y: u32) -> Option<Color> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        Some(self.pixels[(y * self.width + x) as usize].unpremultiply())
+    }
+
+    /// The premultiplied colour at `(x, y)` clamped to `src`.
+    fn get(&self, src: Rect, x: i32, y: i32) -> Color {
+        let x = x.clamp(src.x, src.right() - 1);
+        let y = y.clamp(src.y, src.bottom() - 1);
+        self.pixels[(y * self.width as i32 + x) as usize]
+    }
+
+    /// Samples at a point in image pixels, staying within `src`.
+    fn sample(&self, src: Rect, p: Vector2, filter: Filter) -> Color {
+        match filter {
+            Filter::Nearest => self.get(src, p.x.floor() as i32, p.y.floor() as i32),
+            Filter::Bilinear => {
+                let (x, y) = (p.x - 0.5, p.y - 0.5);
+                let (x0, y0) = (x.floor() as i32, y.floor() as i32);
+                let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+                let top = self.get(src, x0 + 1, y0).lerp(self.get(src, x0, y0), fx);
+                let bottom = self.get(src, x0 + 1, y0 + 1).lerp(self.get(src, x0, y0 + 1), fx);
+                bottom.lerp(top, fy)
+            },
+        }
+    }
+}
+
+impl Canvas {
+    /// Draws the `src` part of `image`, or all of it, stretched over the rect at `pos` with `size`.
+    pub fn draw_image(&mut self, image: &Image, src: Option<Rect>, pos: Vector2, size: Vector2, filter: Filter) {
+        let bounds = Rect::new(0, 0, image.width as i32, image.height as i32);
+        let src = src.map_or(bounds, |r| r.intersection(&bounds));
+        let Some(inverse) = self.transform.inverse() else { return };
+        if src.is_empty() || size.x == 0.0 || size.y == 0.0 {
+            return;
+        }
+
+        let scale = self.transform.scale_factor();
+        let (min, max) = self.transform.bounds(pos, pos + size);
+        let area = Rect::from_bounds(min, max).intersection(&self.clip());
+        let (sx, sy) = (src.width as f32 / size.x, src.height as f32 / size.y);
+        for y in area.y..area.bottom() {
+            for x in area.x..area.right() {
+                let local = inverse.apply(Vector2::new(x as f32 + 0.5, y as f32 + 0.5));
+                // Edges are anti-aliased like any other shape, which matters once the image is rotated
+                let coverage = (0.5 - dist_to_rounded_rect(pos, size, CornerRadii::default(), local) * scale).clamp(0.0, 1.0);
+                if coverage > 0.0 {
+                    let p = Vector2::new(src.x as f32 + (local.x - pos.x) * sx, src.y as f32 + (local.y - pos.y) * sy);
+                    let color = image.sample(src, p, filter).lerp(Color::TRANSPARENT, coverage);
+                    let pixel_index = (y as usize * self.width() as usize + x as usize) * 4;
+                    self.blend(pixel_index, color);
+                }
+            }
+        }
+
+        self.damage(area);
+    }
+}
@@ -0,0 +1,81 @@
+use crate::{canvas::Canvas, color::{BlendMode, Color, PixelFormat}, damage::Damage, paint::Paint, path::{FillRule, Path}, pixel_util::{Rect, Vector2}, raster::rasterize, transform::Transform};
+
+/// Limits where a layer shows when it is composited.
+#[derive(Clone)]
+pub enum LayerMask {
+    /// Only the inside of the path shows, with anti-aliased edges
+    Path(Path, FillRule),
+    /// The alpha of the paint scales the layer, e.g. a gradient to fade it out towards an edge
+    Paint(Paint),
+}
+
+pub(crate) struct Layer {
+    parent: Vec<u8>,
+    parent_damage: Damage,
+    opacity: f32,
+    blend_mode: BlendMode,
+    mask: Option<LayerMask>,
+    transform: Transform,
+}
+
+impl Canvas {
+    /// Draws everything up to the matching [`Canvas::pop_layer`] into a transparent layer,
+    /// which is then composited as a whole, so overlapping primitives fade together.
+    ///
+    /// The mask is in the coordinates current at this call. Pop every layer before presenting,
+    /// until then [`Canvas::data`] holds the layer's pixels as `Argb8888`.
+    pub fn push_layer(&mut self, opacity: f32, blend_mode: BlendMode, mask: Option<LayerMask>) {
+        let layer = vec![0u8; self.buffer.len()];
+        let parent = std::mem::replace(&mut self.buffer, layer);
+        let parent_damage = std::mem::take(&mut self.damage);
+        self.layers.push(Layer {parent, parent_damage, opacity, blend_mode, mask, transform: self.current_transform()});
+    }
+
+    /// Composites the last pushed layer onto the canvas or the layer below it, does nothing without a layer.
+    pub fn pop_layer(&mut self) {
+        let Some(layer) = self.layers.pop() else { return };
+        let pixels = std::mem::replace(&mut self.buffer, layer.parent);
+        let mut touched = std::mem::replace(&mut self.damage, layer.parent_damage);
+
+        let format = self.buffer_format();
+        let width = self.width() as usize;
+        let bounds = Rect::new(0, 0, self.width() as i32, self.height() as i32);
+        let inverse = layer.transform.inverse().unwrap_or_default();
+        let path_mask = match &layer.mask {
+            Some(LayerMask::Path(path, rule)) => {
+                let polygons: Vec<Vec<Vector2>> = path.flatten().into_iter()
+                    .map(|l| l.points.into_iter().map(|p| layer.transform.apply(p)).collect())
+                    .collect();
+                Some(rasterize(&polygons, *rule, bounds))
+            },
+            _ => None,
+        };
+
+        // Damage never overlaps itself, so every pixel is composited once
+        for rect in touched.take() {
+            for y in rect.y..rect.bottom() {
+                for x in rect.x..rect.right() {
+                    let mut coverage = layer.opacity;
+                    match &layer.mask {
+                        Some(LayerMask::Path(..)) => {
+                            let mask = path_mask.as_ref().unwrap();
+                            coverage *= if mask.area.contains(x, y) { mask.get(x, y) } else { 0.0 };
+                        },
+                        Some(LayerMask::Paint(paint)) => {
+                            coverage *= paint.sample(inverse.apply(Vector2::new(x as f32 + 0.5, y as f32 + 0.5))).a as f32 / 255.0;
+                        },
+                        None => {},
+                    }
+
+                    let pixel_index = (y as usize * width + x as usize) * 4;
+                    let p = &pixels[pixel_index..pixel_index + 4];
+                    let src = PixelFormat::Argb8888.decode([p[0], p[1], p[2], p[3]]).lerp(Color::TRANSPARENT, coverage.clamp(0.0, 1.0));
+                    let d = &mut self.buffer[pixel_index..pixel_index + 4];
+                    let dst = format.decode([d[0], d[1], d[2], d[3]]);
+                    d.copy_from_slice(&format.encode(layer.blend_mode.apply(src, dst)));
+                }
+            }
+            self.damage(rect);
+        }
+    }
+}
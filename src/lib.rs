@@ -1,25 +1,735 @@
 pub mod widget;
+pub mod frame;
+pub mod canvas;
+pub mod color;
 pub mod pixel_util;
+pub mod shm;
+pub mod damage;
+pub mod export;
+pub mod path;
+pub mod stroke;
+pub mod paint;
+pub mod transform;
+pub mod image;
+pub mod svg;
+pub mod effects;
+pub mod layer;
+pub mod text;
+pub mod font;
 
 mod macros;
+mod raster;
+mod glyph_cache;
 
 
 #[cfg(test)]
 mod tests {
     
 
-    use std::{thread, time::Duration};
-
     use xkbcommon::xkb::Keysym;
 
-    use crate::{pixel_util, widget::{self, Events}};
+    use crate::{canvas::{Border, Canvas}, color::{BlendMode, Color, PixelFormat}, damage::Damage, font::{FontManager, FontSet}, image::{Filter, Image}, layer::LayerMask, paint::{ExtendMode, Gradient, GradientStop, Paint}, path::{FillRule, Path}, pixel_util::{CornerRadii, Rect, Vector2}, stroke::{LineCap, LineJoin, StrokeStyle}, svg::Svg, text::{measure_text, Align, TextLayout, Wrap}, transform::Transform, widget::{self, Events}};
+
+    #[test]
+    fn canvas_draws_without_compositor() {
+        let mut canvas = Canvas::new(20, 10);
+        canvas.take_damage();
+
+        let red: Color = 0xFF0000FF.into();
+        canvas.draw_rect(red.into(), Vector2::new(2.0, 2.0), Vector2::new(4.0, 3.0));
+
+        assert_eq!(canvas.pixel(2, 2), Some(red));
+        assert_eq!(canvas.pixel(5, 4), Some(red));
+        assert_eq!(canvas.pixel(6, 4), Some(0.into()));
+        assert_eq!(canvas.pixel(20, 0), None);
+        assert_eq!(canvas.take_damage(), vec![Rect::new(2, 2, 4, 3)]);
+    }
+
+    #[test]
+    fn color_honours_pixel_format() {
+        let color: Color = 0x80112233.into();
+        assert_eq!(color, Color::new(0x11, 0x22, 0x33, 0x80));
+        assert_eq!((color.r(), color.g(), color.b(), color.a()), (0x11, 0x22, 0x33, 0x80));
+
+        assert_eq!(PixelFormat::Argb8888.encode(color), [0x33, 0x22, 0x11, 0x80]);
+        assert_eq!(PixelFormat::Xrgb8888.encode(color), [0x33, 0x22, 0x11, 0xFF]);
+
+        let opaque: Color = 0xFF112233.into();
+        for format in [PixelFormat::Argb8888, PixelFormat::Xrgb8888, PixelFormat::Argb2101010, PixelFormat::Xrgb2101010] {
+            assert_eq!(format.decode(format.encode(opaque)), opaque);
+        }
+
+        // 2 bit alpha is rounded and the channels are clamped to it so the pixel stays premultiplied
+        let translucent = PixelFormat::Argb2101010.decode(PixelFormat::Argb2101010.encode(Color::new(200, 200, 200, 200)));
+        assert_eq!(translucent, Color::new(170, 170, 170, 170));
+
+        // 10 bit canvases blend in 8 bits and are only converted when presented
+        let mut canvas = Canvas::with_format(1, 1, PixelFormat::Argb2101010);
+        canvas.draw_rect(0x80FFFFFF.into(), Vector2::new(0.0, 0.0), Vector2::new(1.0, 1.0));
+        assert_eq!(canvas.pixel(0, 0), Some(0x80FFFFFF.into()));
+    }
+
+    #[test]
+    fn translucent_colors_blend_premultiplied() {
+        let mut canvas = Canvas::new(2, 1);
+        canvas.draw_rect(0xFFFFFFFF.into(), Vector2::new(0.0, 0.0), Vector2::new(1.0, 1.0));
+        canvas.draw_rect(0x80000000.into(), Vector2::new(0.0, 0.0), Vector2::new(2.0, 1.0));
+
+        assert_eq!(canvas.pixel(0, 0), Some(0xFF7F7F7F.into()));
+        assert_eq!(canvas.pixel(1, 0), Some(0x80000000.into()));
+
+        canvas.draw_rect(0x80FF0000.into(), Vector2::new(1.0, 0.0), Vector2::new(1.0, 1.0));
+        assert_eq!(&canvas.data()[4..], &[0x00, 0x00, 0x80, 0xC0]);
+
+        canvas.set_blend_mode(BlendMode::Source);
+        canvas.draw_rect(0x40FF0000.into(), Vector2::new(0.0, 0.0), Vector2::new(1.0, 1.0));
+        assert_eq!(canvas.pixel(0, 0), Some(0x40FF0000.into()));
+    }
+
+    #[test]
+    fn lines_are_antialiased() {
+        let mut canvas = Canvas::new(10, 10);
+        canvas.draw_line(0xFF0000FF.into(), Vector2::new(2.0, 5.0), Vector2::new(8.0, 5.0), 1.5);
+
+        assert_eq!(canvas.pixel(5, 5), Some(0xFF0000FF.into()));
+        assert_eq!(canvas.pixel(5, 6).unwrap().a, 0x80);
+        assert_eq!(canvas.pixel(5, 7), Some(Color::TRANSPARENT));
+    }
+
+    #[test]
+    fn circles_and_ellipses() {
+        let mut canvas = Canvas::new(20, 20);
+        let blue: Color = 0xFF0000FF.into();
+        canvas.fill_circle(blue.into(), Vector2::new(10.0, 10.0), 5.0);
+
+        assert_eq!(canvas.pixel(10, 10), Some(blue));
+        assert_eq!(canvas.pixel(13, 10), Some(blue));
+        assert_eq!(canvas.pixel(16, 10), Some(Color::TRANSPARENT));
+        assert!((1..255).contains(&canvas.pixel(13, 13).unwrap().a));
+
+        let mut canvas = Canvas::new(20, 20);
+        canvas.stroke_ellipse(blue.into(), Vector2::new(10.0, 10.0), Vector2::new(8.0, 4.0), 2.0);
+        assert_eq!(canvas.pixel(10, 10), Some(Color::TRANSPARENT));
+        assert_eq!(canvas.pixel(17, 10).unwrap().a, 255);
+        assert_eq!(canvas.pixel(10, 6).unwrap().a, 255);
+        assert_eq!(canvas.pixel(10, 2), Some(Color::TRANSPARENT));
+    }
+
+    #[test]
+    fn rounded_rect_corners_and_border() {
+        let mut canvas = Canvas::new(20, 20);
+        let fill: Color = 0xFF00FF00.into();
+        let border_color: Color = 0xFFFF0000.into();
+        let border = Border::new(2.0, border_color.into());
+        canvas.draw_rounded_rect(fill.into(), Vector2::new(0.0, 0.0), Vector2::new(20.0, 20.0), CornerRadii::new(8.0, 0.0, 0.0, 0.0), Some(border.clone()));
+
+        assert_eq!(canvas.pixel(0, 0), Some(Color::TRANSPARENT));
+        assert_eq!(canvas.pixel(19, 0), Some(border_color));
+        assert_eq!(canvas.pixel(10, 1), Some(border_color));
+        assert_eq!(canvas.pixel(10, 10), Some(fill));
+        assert!((1..255).contains(&canvas.pixel(2, 2).unwrap().a));
+
+        // A negative size extends the rect back from its position, like draw_rect
+        let mut flipped = Canvas::new(20, 20);
+        flipped.draw_rounded_rect(fill.into(), Vector2::new(20.0, 20.0), Vector2::new(-20.0, -20.0), CornerRadii::new(8.0, 0.0, 0.0, 0.0), Some(border));
+        assert_eq!(flipped.data(), canvas.data());
+    }
+
+    #[test]
+    fn paths_fill_with_rules_and_stroke() {
+        let red: Color = 0xFFFF0000.into();
+        let square = |path: Path, min: f32, max: f32| path
+            .move_to(Vector2::new(min, min))
+            .line_to(Vector2::new(max, min))
+            .line_to(Vector2::new(max, max))
+            .line_to(Vector2::new(min, max))
+            .close();
+        let nested = |rule| {
+            let mut canvas = Canvas::new(20, 20);
+            canvas.fill_path(red.into(), &square(square(Path::new(), 0.0, 20.0), 5.0, 15.0), rule);
+            canvas
+        };
+
+        let canvas = nested(FillRule::NonZero);
+        assert_eq!(canvas.pixel(10, 10), Some(red));
+        assert_eq!(canvas.pixel(2, 2), Some(red));
+        let canvas = nested(FillRule::EvenOdd);
+        assert_eq!(canvas.pixel(10, 10), Some(Color::TRANSPARENT));
+        assert_eq!(canvas.pixel(2, 2), Some(red));
+
+        let mut canvas = Canvas::new(20, 20);
+        let triangle = Path::new()
+            .move_to(Vector2::new(10.0, 0.0))
+            .quad_to(Vector2::new(20.0, 10.0), Vector2::new(20.0, 20.0))
+            .line_to(Vector2::new(0.0, 20.0))
+            .close();
+        canvas.fill_path(red.into(), &triangle, FillRule::NonZero);
+        assert_eq!(canvas.pixel(10, 15), Some(red));
+        assert_eq!(canvas.pixel(1, 1), Some(Color::TRANSPARENT));
+
+        let mut canvas = Canvas::new(20, 20);
+        canvas.stroke_path(red.into(), &square(Path::new(), 5.0, 15.0), 2.0);
+        assert_eq!(canvas.pixel(10, 5), Some(red));
+        assert_eq!(canvas.pixel(10, 4), Some(red));
+        assert_eq!(canvas.pixel(10, 10), Some(Color::TRANSPARENT));
+        assert_eq!(canvas.pixel(10, 7), Some(Color::TRANSPARENT));
+    }
+
+    #[test]
+    fn stroke_caps_joins_and_dashes() {
+        let red: Color = 0xFFFF0000.into();
+        let (a, b) = (Vector2::new(5.0, 10.0), Vector2::new(15.0, 10.0));
+
+        let mut canvas = Canvas::new(20, 20);
+        canvas.stroke_line(red.into(), a, b, StrokeStyle::new(4.0).cap(LineCap::Butt));
+        assert_eq!(canvas.pixel(5, 10), Some(red));
+        assert_eq!(canvas.pixel(4, 10), Some(Color::TRANSPARENT));
+
+        let mut canvas = Canvas::new(20, 20);
+        canvas.stroke_line(red.into(), a, b, StrokeStyle::new(4.0).cap(LineCap::Square));
+        assert_eq!(canvas.pixel(3, 8), Some(red));
+        assert_eq!(canvas.pixel(2, 10), Some(Color::TRANSPARENT));
+
+        let corner = Path::new()
+            .move_to(Vector2::new(2.0, 10.0))
+            .line_to(Vector2::new(10.0, 10.0))
+            .line_to(Vector2::new(10.0, 18.0));
+        let mut canvas = Canvas::new(20, 20);
+        canvas.stroke_path(red.into(), &corner, StrokeStyle::new(4.0).join(LineJoin::Miter));
+        assert_eq!(canvas.pixel(11, 8), Some(red));
+        let mut canvas = Canvas::new(20, 20);
+        canvas.stroke_path(red.into(), &corner, StrokeStyle::new(4.0).join(LineJoin::Bevel));
+        assert_eq!(canvas.pixel(11, 8), Some(Color::TRANSPARENT));
+
+        let mut canvas = Canvas::new(20, 20);
+        canvas.stroke_line(red.into(), Vector2::new(0.0, 10.0), Vector2::new(20.0, 10.0), StrokeStyle::new(2.0).cap(LineCap::Butt).dash(vec![4.0], 2.0));
+        assert_eq!(canvas.pixel(0, 10), Some(red));
+        assert_eq!(canvas.pixel(3, 10), Some(Color::TRANSPARENT));
+        assert_eq!(canvas.pixel(7, 10), Some(red));
+        assert_eq!(canvas.pixel(11, 10), Some(Color::TRANSPARENT));
+
+        // Stroke widths are full widths, draw_line's thickness is measured from the centre
+        let mut stroked = Canvas::new(20, 20);
+        stroked.stroke_line(red.into(), Vector2::new(2.0, 10.0), Vector2::new(18.0, 10.0), 4.0);
+        let mut drawn = Canvas::new(20, 20);
+        drawn.draw_line(red.into(), Vector2::new(2.0, 10.0), Vector2::new(18.0, 10.0), 2.0);
+        for canvas in [stroked, drawn] {
+            assert_eq!([7, 8, 11, 12].map(|y| canvas.pixel(10, y)), [Some(Color::TRANSPARENT), Some(red), Some(red), Some(Color::TRANSPARENT)]);
+        }
+
+        // Sweeps beyond a full turn stop after one instead of flattening for ever
+        let mut canvas = Canvas::new(20, 20);
+        canvas.stroke_arc(red.into(), Vector2::new(10.0, 10.0), 6.0, 0.0, 1e9, 2.0);
+        let mut circle = Canvas::new(20, 20);
+        circle.stroke_arc(red.into(), Vector2::new(10.0, 10.0), 6.0, 0.0, std::f32::consts::TAU, 2.0);
+        assert_eq!(canvas.pixel(4, 10), Some(red));
+        assert_eq!(canvas.data(), circle.data());
+    }
+
+    #[test]
+    fn gradients_fill_primitives() {
+        let (red, blue): (Color, Color) = (0xFFFF0000.into(), 0xFF0000FF.into());
+        let gradient = Gradient::new(vec![GradientStop::new(0.0, red), GradientStop::new(1.0, blue)]);
+
+        let mut canvas = Canvas::new(20, 4);
+        canvas.draw_rect(Paint::linear(Vector2::new(0.0, 0.0), Vector2::new(20.0, 0.0), gradient.clone()), Vector2::new(0.0, 0.0), Vector2::new(20.0, 4.0));
+        assert_eq!(canvas.pixel(0, 0).unwrap().r, 249);
+        assert_eq!(canvas.pixel(19, 3).unwrap().b, 249);
+        assert_eq!(canvas.pixel(9, 0), canvas.pixel(9, 3));
+
+        let paint = Paint::linear(Vector2::new(0.0, 0.0), Vector2::new(10.0, 0.0), gradient.clone().extend(ExtendMode::Reflect));
+        assert_eq!(paint.color_at(Vector2::new(15.0, 0.0)), paint.color_at(Vector2::new(5.0, 0.0)));
+        let paint = Paint::linear(Vector2::new(0.0, 0.0), Vector2::new(10.0, 0.0), gradient.clone().extend(ExtendMode::Repeat));
+        assert_eq!(paint.color_at(Vector2::new(12.0, 0.0)), paint.color_at(Vector2::new(2.0, 0.0)));
+        let paint = Paint::linear(Vector2::new(0.0, 0.0), Vector2::new(10.0, 0.0), gradient.clone());
+        assert_eq!(paint.color_at(Vector2::new(30.0, 0.0)), blue);
+
+        let paint = Paint::radial(Vector2::new(10.0, 10.0), 10.0, gradient.clone());
+        assert_eq!(paint.color_at(Vector2::new(10.0, 10.0)), red);
+        assert_eq!(paint.color_at(Vector2::new(10.0, 25.0)), blue);
+
+        // A gauge sweeping from red at the right to blue just before coming back around
+        let mut canvas = Canvas::new(20, 20);
+        canvas.draw_arc(Paint::conic(Vector2::new(10.0, 10.0), 0.0, gradient), Vector2::new(10.0, 10.0), 8.0, 0.0, 2.0 * std::f32::consts::PI, 1.5);
+        let right = canvas.pixel(18, 10).unwrap();
+        let left = canvas.pixel(2, 10).unwrap();
+        assert!(right.r > 200 && right.b < 50);
+        assert!(left.r < 150 && left.b > 100);
+
+        let stops = Gradient::new(vec![]).stop(1.0, blue).stop(0.0, 0x00FF0000.into());
+        assert_eq!(stops.stops()[0].offset, 0.0);
+        let faded = Paint::linear(Vector2::new(0.0, 0.0), Vector2::new(2.0, 0.0), stops).color_at(Vector2::new(1.0, 0.0));
+        assert_eq!((faded.r, faded.b), (0, 255));
+    }
+
+    #[test]
+    fn transforms_apply_to_primitives() {
+        let red: Color = 0xFFFF0000.into();
+        let mut canvas = Canvas::new(20, 20);
+        canvas.save();
+        canvas.translate(10.0, 10.0);
+        canvas.draw_rect(red.into(), Vector2::new(0.0, 0.0), Vector2::new(2.0, 2.0));
+        canvas.save();
+        canvas.scale(2.0, 2.0);
+        canvas.fill_circle(red.into(), Vector2::new(-3.0, -3.0), 1.5);
+        canvas.restore();
+        canvas.draw_rect(red.into(), Vector2::new(3.0, 0.0), Vector2::new(1.0, 1.0));
+        canvas.restore();
+
+        assert_eq!(canvas.pixel(11, 11), Some(red));
+        assert_eq!(canvas.pixel(12, 12), Some(Color::TRANSPARENT));
+        assert_eq!(canvas.pixel(13, 10), Some(red));
+        assert_eq!(canvas.pixel(4, 4), Some(red));
+        assert_eq!(canvas.pixel(5, 4), Some(red));
+        assert_eq!(canvas.pixel(7, 4), Some(Color::TRANSPARENT));
+        assert_eq!(canvas.current_transform(), Transform::IDENTITY);
+
+        // Restoring without a save keeps the transform
+        canvas.translate(1.0, 2.0);
+        canvas.restore();
+        assert_eq!(canvas.current_transform(), Transform::translation(1.0, 2.0));
+
+        // Anisotropic scales report their larger axis
+        assert_eq!(Transform::scaling(4.0, 1.0).scale_factor(), 4.0);
+        assert!((Transform::scaling(1.0, 4.0).then(&Transform::rotation(0.3)).scale_factor() - 4.0).abs() < 1e-4);
+
+        // A bar rotated a quarter turn around its corner points down instead of right
+        let mut canvas = Canvas::new(20, 20);
+        canvas.translate(10.0, 2.0);
+        canvas.rotate(std::f32::consts::FRAC_PI_2);
+        canvas.draw_rect(red.into(), Vector2::new(0.0, 0.0), Vector2::new(12.0, 4.0));
+        assert_eq!(canvas.pixel(8, 10), Some(red));
+        assert_eq!(canvas.pixel(11, 10), Some(Color::TRANSPARENT));
+        assert_eq!(canvas.pixel(8, 15), Some(Color::TRANSPARENT));
+
+        // Gradients follow the coordinates they are drawn in
+        let blue: Color = 0xFF0000FF.into();
+        let gradient = Gradient::new(vec![GradientStop::new(0.0, red), GradientStop::new(1.0, blue)]);
+        canvas.set_transform(Transform::translation(0.0, 10.0));
+        canvas.draw_rect(Paint::linear(Vector2::new(0.0, 0.0), Vector2::new(0.0, 10.0), gradient), Vector2::new(0.0, 0.0), Vector2::new(4.0, 10.0));
+        assert!(canvas.pixel(0, 10).unwrap().r > 240);
+        assert!(canvas.pixel(0, 19).unwrap().b > 240);
+
+        let t = Transform::rotation(0.3).then(&Transform::scaling(2.0, 3.0)).then(&Transform::translation(5.0, -1.0));
+        let p = t.inverse().unwrap().apply(t.apply(Vector2::new(3.0, 4.0)));
+        assert!(p.dist(&Vector2::new(3.0, 4.0)) < 1e-4);
+    }
+
+    #[test]
+    fn images_scale_crop_and_load() {
+        let (red, green, blue, white): (Color, Color, Color, Color) = (0xFFFF0000.into(), 0xFF00FF00.into(), 0xFF0000FF.into(), 0xFFFFFFFF.into());
+        let rgba = [255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255, 255, 255, 255, 255];
+        let image = Image::from_rgba(2, 2, &rgba).unwrap();
+        assert!(Image::from_rgba(3, 2, &rgba).is_err());
+
+        let mut canvas = Canvas::new(8, 8);
+        canvas.draw_image(&image, None, Vector2::new(0.0, 0.0), Vector2::new(4.0, 4.0), Filter::Nearest);
+        assert_eq!(canvas.pixel(1, 1), Some(red));
+        assert_eq!(canvas.pixel(2, 1), Some(green));
+        assert_eq!(canvas.pixel(1, 3), Some(blue));
+        assert_eq!(canvas.pixel(3, 3), Some(white));
+        assert_eq!(canvas.pixel(4, 4), Some(Color::TRANSPARENT));
+
+        canvas.draw_image(&image, Some(Rect::new(1, 0, 1, 1)), Vector2::new(4.0, 4.0), Vector2::new(4.0, 4.0), Filter::Bilinear);
+        assert_eq!(canvas.pixel(4, 4), Some(green));
+        assert_eq!(canvas.pixel(7, 7), Some(green));
+
+        // Negative sizes mirror the image back from its position
+        let mut canvas = Canvas::new(8, 4);
+        canvas.draw_image(&image, None, Vector2::new(8.0, 0.0), Vector2::new(-4.0, 4.0), Filter::Nearest);
+        assert_eq!(canvas.pixel(7, 1), Some(red));
+        assert_eq!(canvas.pixel(5, 1), Some(green));
+        assert_eq!(canvas.pixel(6, 3), Some(blue));
+        assert_eq!(canvas.pixel(4, 3), Some(white));
+        assert_eq!(canvas.pixel(3, 1), Some(Color::TRANSPARENT));
+
+        let mut canvas = Canvas::new(4, 1);
+        canvas.draw_image(&image, Some(Rect::new(0, 0, 2, 1)), Vector2::new(0.0, 0.0), Vector2::new(4.0, 1.0), Filter::Bilinear);
+        assert_eq!(canvas.pixel(0, 0), Some(red));
+        let mixed = canvas.pixel(1, 0).unwrap();
+        assert!(mixed.r > 100 && mixed.g > 50 && mixed.b == 0);
+
+        let mut png = vec![];
+        canvas.write_png(&mut png).unwrap();
+        let loaded = Image::read_png(png.as_slice()).unwrap();
+        assert_eq!((loaded.width(), loaded.height()), (4, 1));
+        assert_eq!(loaded.pixel(1, 0), Some(mixed));
+        assert!(Image::read_jpeg(png.as_slice()).is_err());
+    }
+
+    #[test]
+    fn svg_icons_scale_and_recolor() {
+        let icon = Svg::parse(r##"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="16" viewBox="0 0 10 10">
+            <rect width="5" height="10" fill="#ff0000"/>
+            <g opacity="0.5"><rect x="5" width="5" height="5" fill="#0000ff"/></g>
+            <path d="M5 7.5 H10" stroke="#00ff00" stroke-width="1"/>
+        </svg>"##).unwrap();
+        assert_eq!(icon.size(), Vector2::new(16.0, 16.0));
+        assert!(Svg::parse("<svg").is_err());
+
+        let mut canvas = Canvas::new(20, 20);
+        canvas.draw_svg(&icon, Vector2::new(0.0, 0.0), Vector2::new(20.0, 20.0), None);
+        assert_eq!(canvas.pixel(2, 18), Some(0xFFFF0000.into()));
+        assert_eq!(canvas.pixel(15, 5), Some(0x800000FF.into()));
+        assert_eq!(canvas.pixel(15, 15), Some(0xFF00FF00.into()));
+        assert_eq!(canvas.pixel(15, 12), Some(Color::TRANSPARENT));
+        assert_eq!(canvas.current_transform(), Transform::IDENTITY);
+
+        let mut canvas = Canvas::new(20, 20);
+        canvas.draw_svg(&icon, Vector2::new(10.0, 10.0), Vector2::new(10.0, 10.0), Some(0xFFFFFFFF.into()));
+        assert_eq!(canvas.pixel(11, 11), Some(0xFFFFFFFF.into()));
+        assert_eq!(canvas.pixel(17, 12), Some(0x80FFFFFF.into()));
+        assert_eq!(canvas.pixel(5, 5), Some(Color::TRANSPARENT));
+    }
+
+    #[test]
+    fn blur_and_shadows() {
+        let (red, blue): (Color, Color) = (0xFFFF0000.into(), 0xFF0000FF.into());
+        let mut canvas = Canvas::new(20, 10);
+        canvas.draw_rect(red.into(), Vector2::new(0.0, 0.0), Vector2::new(10.0, 10.0));
+        canvas.draw_rect(blue.into(), Vector2::new(10.0, 0.0), Vector2::new(10.0, 10.0));
+        canvas.take_damage();
+        canvas.blur(Rect::new(5, 0, 10, 10), 4.0);
+
+        assert_eq!(canvas.pixel(0, 5), Some(red));
+        assert_eq!(canvas.pixel(15, 5), Some(blue));
+        let edge = canvas.pixel(9, 5).unwrap();
+        assert!(edge.r > edge.b && edge.b > 50 && edge.a == 255);
+        assert_eq!(canvas.pixel(5, 5), Some(red));
+        assert_eq!(canvas.damaged(), &[Rect::new(5, 0, 10, 10)]);
+
+        let mut canvas = Canvas::new(9, 1);
+        canvas.draw_rect(0xFF000000.into(), Vector2::new(4.0, 0.0), Vector2::new(1.0, 1.0));
+        canvas.box_blur(Rect::new(0, 0, 9, 1), 1);
+        assert_eq!(canvas.pixel(3, 0), Some(0x55000000.into()));
+        assert_eq!(canvas.pixel(4, 0), Some(0x55000000.into()));
+        assert_eq!(canvas.pixel(2, 0), Some(Color::TRANSPARENT));
+
+        let card = Path::rounded_rect(Vector2::new(5.0, 5.0), Vector2::new(20.0, 20.0), 4.0);
+        let mut canvas = Canvas::new(40, 40);
+        canvas.draw_shadow(&card, Vector2::new(4.0, 4.0), 6.0, 0xFF000000.into());
+        assert_eq!(canvas.pixel(19, 19), Some(0xFF000000.into()));
+        let falloff = [29, 31, 33].map(|x| canvas.pixel(x, 19).unwrap().a);
+        assert!(falloff[0] > falloff[1] && falloff[1] > falloff[2] && falloff[0] < 255);
+        assert_eq!(canvas.pixel(39, 19), Some(Color::TRANSPARENT));
+        assert_eq!(canvas.pixel(5, 5).unwrap().a, canvas.pixel(32, 32).unwrap().a);
+        // A negative size extends the rect back from its position
+        let flipped = Path::rounded_rect(Vector2::new(25.0, 25.0), Vector2::new(-20.0, -20.0), 4.0);
+        let mut mirrored = Canvas::new(40, 40);
+        mirrored.draw_shadow(&flipped, Vector2::new(4.0, 4.0), 6.0, 0xFF000000.into());
+        assert_eq!(mirrored.data(), canvas.data());
+        // Huge radii are clamped instead of blurring for ever
+        mirrored.draw_shadow(&flipped, Vector2::new(4.0, 4.0), 1e10, 0xFF000000.into());
+        mirrored.box_blur(Rect::new(0, 0, 40, 40), u32::MAX);
+        mirrored.blur(Rect::new(0, 0, 40, 40), f32::INFINITY);
+
+        let mut canvas = Canvas::new(20, 20);
+        canvas.fill_path(red.into(), &Path::rect(Vector2::new(2.0, 2.0), Vector2::new(6.0, 6.0)), FillRule::NonZero);
+        canvas.fill_path(red.into(), &Path::circle(Vector2::new(14.0, 14.0), 4.0), FillRule::NonZero);
+        assert_eq!(canvas.pixel(2, 2), Some(red));
+        assert_eq!(canvas.pixel(8, 8), Some(Color::TRANSPARENT));
+        assert_eq!(canvas.pixel(14, 14), Some(red));
+        assert_eq!(canvas.pixel(10, 10), Some(Color::TRANSPARENT));
+    }
+
+    #[test]
+    fn layers_composite_as_a_group() {
+        let red: Color = 0xFFFF0000.into();
+        let mut canvas = Canvas::new(20, 10);
+        canvas.draw_rect(red.into(), Vector2::new(0.0, 0.0), Vector2::new(2.0, 2.0));
+        canvas.take_damage();
+
+        canvas.push_layer(0.5, BlendMode::SourceOver, None);
+        assert_eq!(canvas.pixel(0, 0), Some(Color::TRANSPARENT));
+        canvas.draw_rect(red.into(), Vector2::new(4.0, 0.0), Vector2::new(4.0, 4.0));
+        canvas.draw_rect(red.into(), Vector2::new(6.0, 0.0), Vector2::new(4.0, 4.0));
+        canvas.pop_layer();
+        assert_eq!(canvas.pixel(0, 0), Some(red));
+        assert_eq!(canvas.pixel(5, 0), Some(0x80FF0000.into()));
+        assert_eq!(canvas.pixel(7, 0), Some(0x80FF0000.into()));
+        assert_eq!(canvas.damaged(), &[Rect::new(4, 0, 6, 4)]);
+
+        let mut canvas = Canvas::new(20, 10);
+        canvas.push_layer(1.0, BlendMode::SourceOver, Some(LayerMask::Path(Path::circle(Vector2::new(5.0, 5.0), 3.0), FillRule::NonZero)));
+        canvas.draw_rect(red.into(), Vector2::new(0.0, 0.0), Vector2::new(10.0, 10.0));
+        canvas.pop_layer();
+        assert_eq!(canvas.pixel(5, 5), Some(red));
+        assert_eq!(canvas.pixel(1, 1), Some(Color::TRANSPARENT));
+
+        let fade = Gradient::new(vec![GradientStop::new(0.0, 0xFF000000.into()), GradientStop::new(1.0, Color::TRANSPARENT)]);
+        canvas.push_layer(1.0, BlendMode::SourceOver, Some(LayerMask::Paint(Paint::linear(Vector2::new(10.0, 0.0), Vector2::new(20.0, 0.0), fade))));
+        canvas.draw_rect(red.into(), Vector2::new(10.0, 0.0), Vector2::new(10.0, 10.0));
+        canvas.pop_layer();
+        let (near, far) = (canvas.pixel(11, 0).unwrap().a, canvas.pixel(18, 0).unwrap().a);
+        assert!(near > 200 && far < 50);
+
+        canvas.push_layer(1.0, BlendMode::Multiply, None);
+        canvas.draw_rect(0xFF808080.into(), Vector2::new(0.0, 0.0), Vector2::new(10.0, 10.0));
+        canvas.pop_layer();
+        assert_eq!(canvas.pixel(5, 5), Some(0xFF800000.into()));
+        assert_eq!(canvas.pixel(1, 1), Some(0xFF808080.into()));
+
+        // Layers keep alpha on opaque canvases, so only what was drawn into them shows
+        let white: Color = 0xFFFFFFFF.into();
+        let mut canvas = Canvas::with_format(20, 10, PixelFormat::Xrgb8888);
+        canvas.draw_rect(white.into(), Vector2::new(0.0, 0.0), Vector2::new(20.0, 10.0));
+        canvas.push_layer(0.5, BlendMode::SourceOver, None);
+        canvas.fill_circle(red.into(), Vector2::new(10.0, 5.0), 4.0);
+        canvas.pop_layer();
+        assert_eq!(canvas.pixel(6, 1), Some(white));
+        assert_eq!(canvas.pixel(10, 5), Some(0xFFFF7F7F.into()));
+
+        assert_eq!(BlendMode::Screen.apply(0xFF808080.into(), 0xFFFF0000.into()), 0xFFFF8080.into());
+        assert_eq!(BlendMode::Plus.apply(0x80800000.into(), 0x80800000.into()), 0xFFFF0000.into());
+    }
+
+    /// A font from the usual system locations, tests needing one are skipped without it.
+    fn test_font() -> Option<Vec<u8>> {
+        test_font_file("DejaVuSans.ttf")
+    }
+
+    fn test_font_file(name: &str) -> Option<Vec<u8>> {
+        ["/usr/share/fonts/truetype/dejavu", "/usr/share/fonts/TTF", "/usr/share/fonts/dejavu-sans-fonts"]
+            .iter().find_map(|dir| std::fs::read(format!("{dir}/{name}")).ok())
+    }
+
+    #[test]
+    fn glyphs_are_cached() {
+        let Some(data) = test_font() else { return };
+        let font = rusttype::Font::try_from_vec(data.clone()).unwrap();
+        let white: Color = 0xFFFFFFFF.into();
+
+        let mut canvas = Canvas::new(100, 30);
+        canvas.draw_text("12:00".into(), Vector2::new(0.0, 20.0), 16.0, font.clone(), |_, _| white.into(), Color::TRANSPARENT.into());
+        let glyphs = canvas.cached_glyphs();
+        let first = canvas.data().to_vec();
+        assert!(glyphs > 0 && glyphs <= 5);
+        assert!(first.iter().any(|b| *b > 0));
+
+        canvas.set_blend_mode(BlendMode::Source);
+        canvas.draw_rect(Color::TRANSPARENT.into(), Vector2::new(0.0, 0.0), Vector2::new(100.0, 30.0));
+        canvas.set_blend_mode(BlendMode::SourceOver);
+        canvas.draw_text("12:00".into(), Vector2::new(0.0, 20.0), 16.0, font.clone(), |_, _| white.into(), Color::TRANSPARENT.into());
+        assert_eq!(canvas.cached_glyphs(), glyphs);
+        assert_eq!(canvas.data(), first.as_slice());
+
+        // Glyphs of a dropped font are evicted once another font is used
+        let other = rusttype::Font::try_from_vec(data.clone()).unwrap();
+        canvas.draw_text("12:00".into(), Vector2::new(0.0, 20.0), 16.0, other, |_, _| white.into(), Color::TRANSPARENT.into());
+        assert_eq!(canvas.cached_glyphs(), 2 * glyphs);
+        let another = rusttype::Font::try_from_vec(data).unwrap();
+        canvas.draw_text("12:00".into(), Vector2::new(0.0, 20.0), 16.0, another, |_, _| white.into(), Color::TRANSPARENT.into());
+        assert_eq!(canvas.cached_glyphs(), 2 * glyphs);
+
+        canvas.clear_glyph_cache();
+        assert_eq!(canvas.cached_glyphs(), 0);
+    }
+
+    #[test]
+    fn text_is_measured() {
+        let Some(data) = test_font() else { return };
+        let font = rusttype::Font::try_from_vec(data).unwrap();
+
+        let one = measure_text("0", &font, 20.0);
+        let four = measure_text("0000", &font, 20.0);
+        assert!(one.width > 5.0 && (four.width - 4.0 * one.width).abs() < 0.01);
+        assert!(one.ascent > 10.0 && one.descent > 0.0 && one.line_gap >= 0.0);
+        assert_eq!(one.line_height(), one.height() + one.line_gap);
+
+        let (min, max) = one.bounds.unwrap();
+        assert!(min.x >= 0.0 && max.x <= one.width);
+        assert!(-min.y <= one.ascent && min.y < -10.0 && max.y.abs() < 1.0);
+        let (_, max) = measure_text("g", &font, 20.0).bounds.unwrap();
+        assert!(max.y > 2.0 && max.y <= one.descent);
+
+        let blank = measure_text(" ", &font, 20.0);
+        assert!(blank.width > 0.0 && blank.bounds.is_none());
+        assert_eq!(measure_text("", &font, 20.0).width, 0.0);
+
+        // The bounds match the pixels draw_text touches
+        let mut canvas = Canvas::new(40, 40);
+        canvas.take_damage();
+        canvas.draw_text("0".into(), Vector2::new(10.0, 30.0), 20.0, font, |_, _| 0xFFFFFFFF.into(), Color::TRANSPARENT.into());
+        let (min, max) = one.bounds.unwrap();
+        assert_eq!(canvas.damaged(), &[Rect::from_bounds(min + Vector2::new(10.0, 30.0), max + Vector2::new(10.0, 30.0))]);
+    }
+
+    #[test]
+    fn paragraphs_wrap_and_align() {
+        let Some(data) = test_font() else { return };
+        let font = rusttype::Font::try_from_vec(data).unwrap();
+        let text = "one two three four\nfive";
+        let line_height = measure_text("", &font, 10.0).line_height();
+
+        let lines = TextLayout::new().lines(text, &font, 10.0);
+        assert_eq!(lines.iter().map(|l| &text[l.range.clone()]).collect::<Vec<_>>(), ["one two three four", "five"]);
+        assert!((lines[1].pos.y - lines[0].pos.y - line_height).abs() < 0.01);
+
+        let max = measure_text("one two three", &font, 10.0).width + 1.0;
+        let layout = TextLayout::new().max_width(max).line_height(1.5);
+        let lines = layout.lines(text, &font, 10.0);
+        assert_eq!(lines.iter().map(|l| &text[l.range.clone()]).collect::<Vec<_>>(), ["one two three", "four", "five"]);
+        assert!(lines.iter().all(|l| l.width <= max && l.pos.x == 0.0));
+        assert!((lines[2].pos.y - lines[1].pos.y - line_height * 1.5).abs() < 0.01);
+        assert_eq!(layout.measure(text, &font, 10.0).x, max);
+
+        let lines = layout.clone().align(Align::Right).lines(text, &font, 10.0);
+        assert!(lines.iter().all(|l| (l.pos.x + l.width - max).abs() < 0.01));
+        let lines = layout.clone().align(Align::Center).lines(text, &font, 10.0);
+        assert!((lines[1].pos.x * 2.0 + lines[1].width - max).abs() < 0.01);
+        let lines = layout.clone().align(Align::Justify).lines(text, &font, 10.0);
+        assert!((lines[0].width + 2.0 * lines[0].word_spacing - max).abs() < 0.01);
+        assert_eq!(lines[1].word_spacing, 0.0);
+
+        // Words that don't fit a line on their own are broken between characters
+        let narrow = TextLayout::new().max_width(measure_text("thr", &font, 10.0).width + 0.5);
+        let lines = narrow.lines("three", &font, 10.0);
+        assert_eq!(lines.iter().map(|l| &"three"[l.range.clone()]).collect::<Vec<_>>(), ["thr", "ee"]);
+        let lines = narrow.wrap(Wrap::Character).lines("a three", &font, 10.0);
+        assert!(&"a three"[lines[0].range.clone()] != "a" && "a three"[lines[0].range.clone()].starts_with("a t"));
+
+        // Widths below zero break between every character instead of looping forever
+        let lines = TextLayout::new().max_width(-1.0).lines("hi", &font, 10.0);
+        assert_eq!(lines.iter().map(|l| &"hi"[l.range.clone()]).collect::<Vec<_>>(), ["h", "i"]);
+        assert_eq!(TextLayout::new().max_width(f32::NAN).lines("hi", &font, 10.0).len(), 1);
+
+        let mut canvas = Canvas::new(120, 60);
+        canvas.take_damage();
+        canvas.draw_paragraph(text, Vector2::new(0.0, 0.0), 10.0, font, &layout.align(Align::Justify), |_, _| 0xFFFFFFFF.into());
+        let damaged = canvas.damaged().iter().fold(Rect::new(0, 0, 0, 0), |a, b| a.union(b));
+        assert!(damaged.right() as f32 >= max - 1.0 && damaged.right() as f32 <= max + 1.0);
+        assert!(damaged.bottom() as f32 > line_height * 3.0);
+    }
+
+    #[test]
+    fn missing_glyphs_fall_back() {
+        let (Some(sans), Some(mono)) = (test_font(), test_font_file("DejaVuSansMono.ttf")) else { return };
+        let sans = rusttype::Font::try_from_vec(sans).unwrap();
+        let mono = rusttype::Font::try_from_vec(mono).unwrap();
+        let fonts = FontSet::new(mono.clone()).fallback(sans.clone());
+
+        // DejaVu Sans Mono has no reference mark, DejaVu Sans does
+        assert!(std::ptr::eq(fonts.font_for('a'), &fonts.fonts()[0]));
+        assert!(std::ptr::eq(fonts.font_for('※'), &fonts.fonts()[1]));
+        assert!(std::ptr::eq(fonts.font_for('\u{10FFFF}'), &fonts.fonts()[0]));
+
+        let width = measure_text("a※", &fonts, 20.0).width;
+        assert!((width - measure_text("a", &mono, 20.0).width - measure_text("※", &sans, 20.0).width).abs() < 0.01);
+        assert_eq!(measure_text("a", &fonts, 20.0), measure_text("a", &mono, 20.0));
+
+        let white: Color = 0xFFFFFFFF.into();
+        let draw = |font: FontSet| {
+            let mut canvas = Canvas::new(30, 30);
+            canvas.draw_text("※".into(), Vector2::new(2.0, 22.0), 20.0, font, |_, _| white.into(), Color::TRANSPARENT.into());
+            (0..30).flat_map(|y| (0..30).map(move |x| (x, y))).filter(|&(x, y)| canvas.pixel(x, y).unwrap().a > 0).count()
+        };
+        let (alone, fallback, direct) = (draw(mono.into()), draw(fonts), draw(sans.into()));
+        assert_eq!(fallback, direct);
+        assert_ne!(alone, direct);
+    }
+
+    #[test]
+    fn fonts_are_found_by_family() {
+        let Some(dir) = ["/usr/share/fonts/truetype/dejavu", "/usr/share/fonts/TTF", "/usr/share/fonts/dejavu-sans-fonts"]
+            .into_iter().find(|dir| std::path::Path::new(dir).join("DejaVuSans.ttf").exists()) else { return };
+        let fonts = FontManager::from_dirs([dir, dir]);
+        assert!(fonts.families().contains(&"DejaVu Sans Mono"));
+
+        let shared = |a: &rusttype::Font, b: &rusttype::Font| matches!((a, b), (rusttype::Font::Ref(a), rusttype::Font::Ref(b)) if std::sync::Arc::ptr_eq(a, b));
+        let regular = fonts.font("dejavu sans").unwrap();
+        let bold = fonts.font("DejaVu Sans:weight=bold").unwrap();
+        assert!(shared(&regular, &fonts.font("Missing Family,DejaVu Sans:regular").unwrap()));
+        assert!(shared(&bold, &fonts.font("DejaVu Sans:bold").unwrap()));
+        assert!(!shared(&regular, &bold));
+        assert!(!shared(&bold, &fonts.font("DejaVu Sans:weight=700:slant=oblique").unwrap()));
+        // Without a light face the closest weight is used
+        assert!(shared(&regular, &fonts.font("DejaVu Sans:weight=350").unwrap()));
+
+        let set = fonts.font_set("DejaVu Sans Mono,Missing Family,DejaVu Sans").unwrap();
+        assert_eq!(set.fonts().len(), 2);
+        assert!(std::ptr::eq(set.font_for('※'), &set.fonts()[1]));
+
+        // Condensed faces share the family but only win when nothing else matches
+        let links = tempfile::tempdir().unwrap();
+        std::os::unix::fs::symlink(format!("{dir}/DejaVuSansCondensed.ttf"), links.path().join("a.ttf")).unwrap();
+        std::os::unix::fs::symlink(format!("{dir}/DejaVuSans.ttf"), links.path().join("b.ttf")).unwrap();
+        let advance = |font: &rusttype::Font| font.glyph('m').scaled(rusttype::Scale::uniform(10.0)).h_metrics().advance_width;
+        assert_eq!(advance(&FontManager::from_dirs([links.path()]).font("DejaVu Sans").unwrap()), advance(&regular));
+
+        assert!(matches!(fonts.font("Missing Family"), Err(widget::WidgetError::FontNotFound(_))));
+        assert!(matches!(fonts.font("DejaVu Sans:weight=heavyish"), Err(widget::WidgetError::InvalidFontQuery(_))));
+        assert!(matches!(fonts.font(":bold"), Err(widget::WidgetError::InvalidFontQuery(_))));
+    }
+
+    #[test]
+    fn primitives_respect_clip_and_bounds() {
+        let mut canvas = Canvas::new(10, 10);
+        let red: Color = 0xFFFF0000.into();
+
+        // Would wrap onto the next row or index past the buffer without clipping
+        canvas.draw_rect(red.into(), Vector2::new(8.0, 9.0), Vector2::new(5.0, 5.0));
+        canvas.draw_where(red.into(), |_| true, Vector2::new(-5.0, -5.0), Vector2::new(15.0, 15.0));
+        canvas.draw_line(red.into(), Vector2::new(0.0, 0.0), Vector2::new(20.0, 20.0), 3.0);
+
+        let mut canvas = Canvas::new(10, 10);
+        canvas.push_clip(Rect::new(2, 2, 6, 6));
+        canvas.push_clip(Rect::new(0, 0, 4, 4));
+        assert_eq!(canvas.clip(), Rect::new(2, 2, 2, 2));
+        canvas.draw_rect(red.into(), Vector2::new(0.0, 0.0), Vector2::new(10.0, 10.0));
+        canvas.pop_clip();
+        canvas.pop_clip();
+
+        assert_eq!(canvas.pixel(2, 2), Some(red));
+        assert_eq!(canvas.pixel(3, 3), Some(red));
+        assert_eq!(canvas.pixel(4, 3), Some(Color::TRANSPARENT));
+        assert_eq!(canvas.pixel(1, 1), Some(Color::TRANSPARENT));
+        assert_eq!(canvas.clip(), Rect::new(0, 0, 10, 10));
+
+        // Coordinates beyond i32 neither overflow nor lose the part inside the canvas
+        let mut canvas = Canvas::new(10, 10);
+        canvas.draw_rect(red.into(), Vector2::new(-3e9, -3e9), Vector2::new(6e9, 6e9));
+        assert_eq!(canvas.pixel(5, 5), Some(red));
+        let mut canvas = Canvas::new(10, 10);
+        canvas.draw_line(red.into(), Vector2::new(0.0, 5.0), Vector2::new(3e9, 5.0), 1.0);
+        let far = Path::new().move_to(Vector2::new(-3e9, -3e9)).line_to(Vector2::new(3e9, -3e9)).line_to(Vector2::new(0.0, 3e9)).close();
+        canvas.fill_path(red.into(), &far, FillRule::NonZero);
+        assert_eq!(canvas.pixel(0, 0), Some(red));
+    }
+
+    #[test]
+    fn export_converts_argb8888() {
+        let mut canvas = Canvas::new(2, 1);
+        canvas.data_mut()[..4].copy_from_slice(&[0x10, 0x20, 0x30, 0xFF]);
+
+        let mut ppm = vec![];
+        canvas.write_ppm(&mut ppm).unwrap();
+        assert_eq!(ppm, b"P6\n2 1\n255\n\x30\x20\x10\0\0\0");
+
+        let mut png = vec![];
+        canvas.write_png(&mut png).unwrap();
+        let mut reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+        let mut rgba = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut rgba).unwrap();
+        assert_eq!(rgba, [0x30, 0x20, 0x10, 0xFF, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn damage_merges_touching_rects() {
+        let mut damage = Damage::new();
+        damage.add(Rect::new(0, 0, 10, 10));
+        damage.add(Rect::new(100, 0, 10, 10));
+        damage.add(Rect::new(5, 5, 10, 10));
+        damage.add(Rect::new(0, 0, 0, 10));
+
+        assert_eq!(damage.rects().len(), 2);
+        assert!(damage.rects().contains(&Rect::new(0, 0, 15, 15)));
+        assert!(damage.rects().contains(&Rect::new(100, 0, 10, 10)));
+    }
 
     #[test]
     fn test() {
-        use std::{f32::consts::PI, fs::File, io::Read};
+        use std::f32::consts::PI;
 
-        use pixel_util::Vector2;
-        use rusttype::Font;
         use wayland_client::Connection;
         use wayland_protocols_wlr::layer_shell::v1::client::{zwlr_layer_shell_v1::Layer, zwlr_layer_surface_v1::Anchor};
         use widget::WidgetBuilder;
@@ -30,35 +740,26 @@ mod tests {
             .anchor(Anchor::Top)
             .exclusive_zone(1)
             .kb_interactivity(wayland_protocols_wlr::layer_shell::v1::client::zwlr_layer_surface_v1::KeyboardInteractivity::OnDemand,
-                Events::new(move |key, comp| {
+                Events::new(|key, widget| {
                     println!("pressed: {:?}", key);
                     if key == Keysym::Escape {
                         println!("Escape!");
-                        comp.unwrap().write().unwrap().close();
+                        widget.close();
                     }
-                }, |key, comp| println!("released: {:?}", key)))
+                }, |key, _widget| println!("released: {:?}", key)))
             .build();
 
         widget.create_surface("rust-widget".into()).unwrap();
 
     
-        let path = "/media/Drive2/idea-IC-233.14475.28/jbr/lib/fonts/JetBrainsMono-Medium.ttf";
-        let mut file = File::open(path).expect("Failed to load font");
-        let mut font_data = Vec::new();
-        file.read_to_end(&mut font_data).expect("Failed to read font file");
-        let font = Font::try_from_vec(font_data).expect("Failed to parse font");
+        let font = FontManager::system().font("JetBrains Mono,monospace:weight=medium").expect("Failed to load font");
     
 
-        widget.get_comp().unwrap().draw_rect(0x00FF0000.into(), Vector2::new(0.0,0.0), Vector2::new(300.0,100.0)).unwrap();
-        widget.get_comp().unwrap().draw_text("Hello, World!".into(), Vector2::new(0.0, 40.0), 32.0, font, |_,_| 0xFF0000FF.into(), 0x00FF0000.into()).unwrap();
-        //widget.draw_line(0xFF00FF00.into(), Vector2::new(0.0,0.0), Vector2::new(100.0,100.0), 10.0).unwrap();
-        widget.get_comp().unwrap().draw_arc(0xFF00FF00.into(), Vector2::new(50.0, 50.0), 50.0, 0.0, PI, 10.0).unwrap();
-
-        widget.get_comp().unwrap().update_blocking().unwrap();
-
-        while widget.get_comp().unwrap().running {
-            widget.get_comp().unwrap().update().unwrap();
-            thread::sleep(Duration::from_millis(100));
-        };
+        widget.get_comp().unwrap().run(|frame| {
+            frame.draw_rect(0x00FF0000.into(), Vector2::new(0.0,0.0), Vector2::new(300.0,100.0));
+            frame.draw_text("Hello, World!".into(), Vector2::new(0.0, 40.0), 32.0, font.clone(), |_,_| 0xFF0000FF.into(), 0x00FF0000.into());
+            //frame.draw_line(0xFF00FF00.into(), Vector2::new(0.0,0.0), Vector2::new(100.0,100.0), 10.0);
+            frame.draw_arc(0xFF00FF00.into(), Vector2::new(50.0, 50.0), 50.0, 0.0, PI, 10.0);
+        }).unwrap();
     }
 }
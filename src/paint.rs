@@ -0,0 +1,160 @@
+use std::f32::consts::PI;
+
+use crate::{color::Color, pixel_util::{PolarVector2, Vector2}};
+
+/// What a gradient does past its first and last stop.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ExtendMode {
+    /// Keeps the colour of the nearest stop
+    #[default]
+    Pad,
+    /// Starts over from the first stop
+    Repeat,
+    /// Runs back and forth between the stops
+    Reflect,
+}
+
+/// A colour at a position between 0 and 1 along a gradient.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GradientStop {
+    pub offset: f32,
+    pub color: Color,
+}
+
+impl GradientStop {
+    pub fn new(offset: f32, color: Color) -> Self {
+        Self {offset, color}
+    }
+}
+
+/// Colour stops and the extend mode shared by every gradient shape.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Gradient {
+    stops: Vec<GradientStop>,
+    extend: ExtendMode,
+}
+
+impl Gradient {
+    /// A gradient through `stops`, they don't need to be sorted.
+    pub fn new(stops: Vec<GradientStop>) -> Self {
+        let mut gradient = Self {stops: vec![], extend: ExtendMode::default()};
+        for stop in stops {
+            gradient = gradient.stop(stop.offset, stop.color);
+        }
+        gradient
+    }
+
+    /// Adds a stop, a stop at the same offset as an earlier one makes a hard edge.
+    pub fn stop(mut self, offset: f32, color: Color) -> Self {
+        let index = self.stops.partition_point(|s| s.offset <= offset);
+        self.stops.insert(index, GradientStop::new(offset, color));
+        self
+    }
+
+    pub fn extend(mut self, extend: ExtendMode) -> Self {
+        self.extend = extend;
+        self
+    }
+
+    pub fn stops(&self) -> &[GradientStop] {
+        &self.stops
+    }
+
+    /// The premultiplied colour at `t`, interpolated in premultiplied space
+    /// so fading into a transparent stop doesn't darken.
+    fn sample(&self, t: f32) -> Color {
+        let t = match self.extend {
+            ExtendMode::Pad => t,
+            ExtendMode::Repeat => t.rem_euclid(1.0),
+            ExtendMode::Reflect => {
+                let t = t.rem_euclid(2.0);
+                if t > 1.0 { 2.0 - t } else { t }
+            },
+        };
+
+        let (first, last) = match (self.stops.first(), self.stops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Color::TRANSPARENT,
+        };
+        if t <= first.offset {
+            return first.color.premultiply();
+        }
+        if t >= last.offset {
+            return last.color.premultiply();
+        }
+        let index = self.stops.partition_point(|s| s.offset <= t);
+        let (a, b) = (self.stops[index - 1], self.stops[index]);
+        let value = (t - a.offset) / (b.offset - a.offset);
+        b.color.premultiply().lerp(a.color.premultiply(), value)
+    }
+}
+
+/// How a shape is filled, accepted by every primitive that takes a colour.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Paint {
+    Solid(Color),
+    /// Varies along the line from `start` to `end`, constant across it
+    Linear { start: Vector2, end: Vector2, gradient: Gradient },
+    /// Varies with the distance from `center`, reaching the end at `radius`
+    Radial { center: Vector2, radius: f32, gradient: Gradient },
+    /// Sweeps around `center` starting at `angle`, in radians like [`Canvas::draw_arc`](crate::canvas::Canvas::draw_arc)
+    Conic { center: Vector2, angle: f32, gradient: Gradient },
+}
+
+impl Paint {
+    pub fn linear(start: Vector2, end: Vector2, gradient: Gradient) -> Self {
+        Self::Linear {start, end, gradient}
+    }
+
+    pub fn radial(center: Vector2, radius: f32, gradient: Gradient) -> Self {
+        Self::Radial {center, radius, gradient}
+    }
+
+    pub fn conic(center: Vector2, angle: f32, gradient: Gradient) -> Self {
+        Self::Conic {center, angle, gradient}
+    }
+
+    /// The colour at a point, with straight alpha.
+    pub fn color_at(&self, p: Vector2) -> Color {
+        self.sample(p).unpremultiply()
+    }
+
+    /// The premultiplied colour at a point.
+    pub(crate) fn sample(&self, p: Vector2) -> Color {
+        match self {
+            Self::Solid(color) => color.premultiply(),
+            Self::Linear {start, end, gradient} => {
+                let (d, v) = (*end - *start, p - *start);
+                let len = d.x * d.x + d.y * d.y;
+                let t = if len > 0.0 { (v.x * d.x + v.y * d.y) / len } else { 0.0 };
+                gradient.sample(t)
+            },
+            Self::Radial {center, radius, gradient} => {
+                let t = if *radius > 0.0 { p.dist(center) / radius } else { 1.0 };
+                gradient.sample(t)
+            },
+            Self::Conic {center, angle, gradient} => {
+                let theta = PolarVector2::from(p - *center).theta;
+                gradient.sample(((theta - angle) / (2.0 * PI)).rem_euclid(1.0))
+            },
+        }
+    }
+}
+
+impl Default for Paint {
+    fn default() -> Self {
+        Self::Solid(Color::TRANSPARENT)
+    }
+}
+
+impl From<Color> for Paint {
+    fn from(value: Color) -> Self {
+        Self::Solid(value)
+    }
+}
+
+impl From<u32> for Paint {
+    fn from(value: u32) -> Self {
+        Self::Solid(value.into())
+    }
+}
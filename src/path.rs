@@ -0,0 +1,228 @@
+use std::f32::consts::{PI, TAU};
+
+use crate::{canvas::Canvas, paint::Paint, pixel_util::{CornerRadii, PolarVector2, Vector2}};
+
+/// How far flattened curves may stray from the real curve, in pixels.
+const TOLERANCE: f32 = 0.2;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum FillRule {
+    /// Inside where the outline winds around a point any number of times
+    #[default]
+    NonZero,
+    /// Inside where a ray from a point crosses the outline an odd number of times
+    EvenOdd,
+}
+
+#[derive(Copy, Clone)]
+pub enum PathCommand {
+    MoveTo(Vector2),
+    LineTo(Vector2),
+    QuadTo(Vector2, Vector2),
+    CubicTo(Vector2, Vector2, Vector2),
+    /// Circular arc around a centre, angles in radians like [`Canvas::draw_arc`]
+    ArcTo { center: Vector2, radius: f32, start: f32, end: f32 },
+    Close,
+}
+
+/// A polyline approximating one subpath.
+pub struct Polyline {
+    pub points: Vec<Vector2>,
+    pub closed: bool,
+}
+
+/// A vector outline built from lines and curves, filled with [`Canvas::fill_path`]
+/// and stroked with [`Canvas::stroke_path`].
+#[derive(Clone, Default)]
+pub struct Path {
+    commands: Vec<PathCommand>,
+}
+
+impl Path {
+    pub fn new() -> Self {
+        Self {commands: vec![]}
+    }
+
+    pub fn rect(pos: Vector2, size: Vector2) -> Self {
+        Self::new()
+            .move_to(pos)
+            .line_to(Vector2::new(pos.x + size.x, pos.y))
+            .line_to(pos + size)
+            .line_to(Vector2::new(pos.x, pos.y + size.y))
+            .close()
+    }
+
+    /// The outline [`Canvas::draw_rounded_rect`] fills, radii are limited to half the shorter side.
+    pub fn rounded_rect<R: Into<CornerRadii>>(pos: Vector2, size: Vector2, radii: R) -> Self {
+        let radii = radii.into();
+        // A negative size extends the rect back from `pos`
+        let (pos, size) = (Vector2::new(pos.x.min(pos.x + size.x), pos.y.min(pos.y + size.y)), Vector2::new(size.x.abs(), size.y.abs()));
+        let r = |r: f32| r.clamp(0.0, size.x.min(size.y) / 2.0);
+        let (tl, tr, br, bl) = (r(radii.top_left), r(radii.top_right), r(radii.bottom_right), r(radii.bottom_left));
+        let (right, bottom) = (pos.x + size.x, pos.y + size.y);
+        Self::new()
+            .arc_to(Vector2::new(pos.x + tl, pos.y + tl), tl, PI, 1.5 * PI)
+            .arc_to(Vector2::new(right - tr, pos.y + tr), tr, -0.5 * PI, 0.0)
+            .arc_to(Vector2::new(right - br, bottom - br), br, 0.0, 0.5 * PI)
+            .arc_to(Vector2::new(pos.x + bl, bottom - bl), bl, 0.5 * PI, PI)
+            .close()
+    }
+
+    pub fn circle(center: Vector2, radius: f32) -> Self {
+        Self::new().arc_to(center, radius, 0.0, 2.0 * PI).close()
+    }
+
+    pub fn commands(&self) -> &[PathCommand] {
+        &self.commands
+    }
+
+    pub fn move_to(mut self, p: Vector2) -> Self {
+        self.commands.push(PathCommand::MoveTo(p));
+        self
+    }
+
+    pub fn line_to(mut self, p: Vector2) -> Self {
+        self.commands.push(PathCommand::LineTo(p));
+        self
+    }
+
+    pub fn quad_to(mut self, c: Vector2, p: Vector2) -> Self {
+        self.commands.push(PathCommand::QuadTo(c, p));
+        self
+    }
+
+    pub fn cubic_to(mut self, c1: Vector2, c2: Vector2, p: Vector2) -> Self {
+        self.commands.push(PathCommand::CubicTo(c1, c2, p));
+        self
+    }
+
+    /// Adds an arc from `start` to `end`, joined to the current point by a straight line.
+    ///
+    /// Arcs sweeping more than a full turn stop after one, at `start ± TAU`.
+    pub fn arc_to(mut self, center: Vector2, radius: f32, start: f32, end: f32) -> Self {
+        self.commands.push(PathCommand::ArcTo {center, radius, start, end});
+        self
+    }
+
+    pub fn close(mut self) -> Self {
+        self.commands.push(PathCommand::Close);
+        self
+    }
+
+    /// Approximates every subpath with straight segments.
+    pub fn flatten(&self) -> Vec<Polyline> {
+        self.flatten_with_tolerance(TOLERANCE)
+    }
+
+    /// Like [`Path::flatten`], `tolerance` is how far segments may stray from the curves.
+    pub(crate) fn flatten_with_tolerance(&self, tolerance: f32) -> Vec<Polyline> {
+        let mut lines: Vec<Polyline> = vec![];
+        let mut current: Vec<Vector2> = vec![];
+        let mut last = Vector2::new(0.0, 0.0);
+
+        let finish = |lines: &mut Vec<Polyline>, current: &mut Vec<Vector2>, closed: bool| {
+            if current.len() > 1 {
+                lines.push(Polyline {points: std::mem::take(current), closed});
+            }
+            current.clear();
+        };
+
+        for command in &self.commands {
+            match *command {
+                PathCommand::MoveTo(p) => {
+                    finish(&mut lines, &mut current, false);
+                    current.push(p);
+                },
+                PathCommand::LineTo(p) => {
+                    if current.is_empty() {
+                        current.push(last);
+                    }
+                    current.push(p);
+                },
+                PathCommand::QuadTo(c, p) => {
+                    if current.is_empty() {
+                        current.push(last);
+                    }
+                    let n = segments(last.dist(&c) + c.dist(&p), tolerance);
+                    for i in 1..=n {
+                        let t = i as f32 / n as f32;
+                        let mt = 1.0 - t;
+                        current.push(Vector2::new(
+                            mt * mt * last.x + 2.0 * mt * t * c.x + t * t * p.x,
+                            mt * mt * last.y + 2.0 * mt * t * c.y + t * t * p.y,
+                        ));
+                    }
+                },
+                PathCommand::CubicTo(c1, c2, p) => {
+                    if current.is_empty() {
+                        current.push(last);
+                    }
+                    let n = segments(last.dist(&c1) + c1.dist(&c2) + c2.dist(&p), tolerance);
+                    for i in 1..=n {
+                        let t = i as f32 / n as f32;
+                        let mt = 1.0 - t;
+                        let (a, b, c, d) = (mt * mt * mt, 3.0 * mt * mt * t, 3.0 * mt * t * t, t * t * t);
+                        current.push(Vector2::new(
+                            a * last.x + b * c1.x + c * c2.x + d * p.x,
+                            a * last.y + b * c1.y + c * c2.y + d * p.y,
+                        ));
+                    }
+                },
+                PathCommand::ArcTo {center, radius, start, end} => {
+                    // Largest step that keeps the chord within the tolerance
+                    let step = 2.0 * (1.0 - tolerance / radius.max(tolerance)).acos().max(PI / 180.0);
+                    let sweep = (end - start).clamp(-TAU, TAU);
+                    let n = (sweep.abs() / step).ceil().max(1.0) as usize;
+                    for i in 0..=n {
+                        let theta = start + sweep * i as f32 / n as f32;
+                        current.push(Vector2::from(PolarVector2::new(radius, theta)) + center);
+                    }
+                },
+                PathCommand::Close => {
+                    let first = current.first().copied();
+                    finish(&mut lines, &mut current, true);
+                    if let Some(first) = first {
+                        current.push(first);
+                    }
+                    last = first.unwrap_or(last);
+                    continue;
+                },
+            }
+            last = *current.last().unwrap();
+        }
+        finish(&mut lines, &mut current, false);
+        lines
+    }
+}
+
+fn segments(length: f32, tolerance: f32) -> usize {
+    ((length / tolerance).sqrt().ceil() as usize).clamp(1, 256)
+}
+
+/// Signed area of a polygon, positive when wound clockwise on screen.
+pub(crate) fn signed_area(points: &[Vector2]) -> f32 {
+    let mut area = 0.0;
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        area += a.x * b.y - b.x * a.y;
+    }
+    area / 2.0
+}
+
+pub(crate) fn circle_polygon(center: Vector2, radius: f32) -> Vec<Vector2> {
+    let step = 2.0 * (1.0 - TOLERANCE / radius.max(TOLERANCE)).acos().max(PI / 180.0);
+    let n = ((2.0 * PI / step).ceil() as usize).max(8);
+    (0..n).map(|i| Vector2::from(PolarVector2::new(radius, 2.0 * PI * i as f32 / n as f32)) + center).collect()
+}
+
+impl Canvas {
+    pub fn fill_path(&mut self, paint: Paint, path: &Path, rule: FillRule) {
+        let polygons: Vec<Vec<Vector2>> = path.flatten_with_tolerance(self.local_tolerance()).into_iter().map(|l| l.points).collect();
+        self.fill_polygons(&paint, &polygons, rule);
+    }
+
+    /// The flattening tolerance in current coordinates, so curves stay smooth when scaled up.
+    pub(crate) fn local_tolerance(&self) -> f32 {
+        TOLERANCE / self.current_transform().scale_factor().max(1e-3)
+    }
+}
@@ -1,4 +1,4 @@
-use std::ops::{Add, Sub};
+use std::ops::{Add, Mul, Sub};
 
 use rusttype::Point;
 
@@ -9,7 +9,7 @@ pub struct PolarVector2 {
 }
 
 impl PolarVector2 {
-    fn new(r: f32, theta: f32) -> Self {
+    pub fn new(r: f32, theta: f32) -> Self {
         Self {r, theta}
     }
 }
@@ -22,7 +22,7 @@ impl From<Vector2> for PolarVector2 {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Vector2 {
     pub x: f32,
     pub y: f32,
@@ -65,6 +65,14 @@ impl Sub for Vector2 {
     }
 }
 
+impl Mul<f32> for Vector2 {
+    type Output = Vector2;
+
+    fn mul(self, rhs: f32) -> Self::Output {
+        Self::Output {x: self.x * rhs, y: self.y * rhs}
+    }
+}
+
 impl Add for Vector2 {
     type Output = Vector2;
 
@@ -73,6 +81,89 @@ impl Add for Vector2 {
     }
 }
 
+/// An axis-aligned box of whole pixels.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Rect {
+    pub fn new(x: i32, y: i32, width: i32, height: i32) -> Self {
+        Self {x, y, width, height}
+    }
+
+    /// The smallest rect covering every pixel touched between `min` and `max`.
+    ///
+    /// Bounds beyond ±2^29 are clamped, far outside any canvas, so the size always fits an `i32`.
+    pub fn from_bounds(min: Vector2, max: Vector2) -> Self {
+        const LIMIT: f32 = (1 << 29) as f32;
+        let x = min.x.floor().clamp(-LIMIT, LIMIT) as i32;
+        let y = min.y.floor().clamp(-LIMIT, LIMIT) as i32;
+        Self::new(x, y, max.x.ceil().clamp(-LIMIT, LIMIT) as i32 - x, max.y.ceil().clamp(-LIMIT, LIMIT) as i32 - y)
+    }
+
+    pub fn right(&self) -> i32 {
+        self.x.saturating_add(self.width)
+    }
+
+    pub fn bottom(&self) -> i32 {
+        self.y.saturating_add(self.height)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width <= 0 || self.height <= 0
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x && x < self.right() && y >= self.y && y < self.bottom()
+    }
+
+    /// Whether the rects overlap or share an edge.
+    pub fn touches(&self, other: &Rect) -> bool {
+        self.x <= other.right() && other.x <= self.right() && self.y <= other.bottom() && other.y <= self.bottom()
+    }
+
+    pub fn union(&self, other: &Rect) -> Rect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Rect::new(x, y, self.right().max(other.right()).saturating_sub(x), self.bottom().max(other.bottom()).saturating_sub(y))
+    }
+
+    /// The overlapping part of both rects, empty if they don't overlap.
+    pub fn intersection(&self, other: &Rect) -> Rect {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        Rect::new(x, y, self.right().min(other.right()).saturating_sub(x).max(0), self.bottom().min(other.bottom()).saturating_sub(y).max(0))
+    }
+}
+
+/// Radius of each corner of a rounded rect.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct CornerRadii {
+    pub top_left: f32,
+    pub top_right: f32,
+    pub bottom_right: f32,
+    pub bottom_left: f32,
+}
+
+impl CornerRadii {
+    pub fn new(top_left: f32, top_right: f32, bottom_right: f32, bottom_left: f32) -> Self {
+        Self {top_left, top_right, bottom_right, bottom_left}
+    }
+
+    pub fn uniform(radius: f32) -> Self {
+        Self::new(radius, radius, radius, radius)
+    }
+}
+
+impl From<f32> for CornerRadii {
+    fn from(value: f32) -> Self {
+        Self::uniform(value)
+    }
+}
 
 pub fn dist_to_line(a: Vector2, b: Vector2, other: Vector2) -> f32 {
     let l_squared = a.square_dist(&b);
@@ -110,3 +201,37 @@ pub fn dist_to_arc(center: Vector2, radius: f32, start: f32, end: f32, other: Ve
         other.dist(&(Into::<Vector2>::into(PolarVector2 {r: radius, theta: end}) + center))
     }
 }
+
+/// Signed distance to the outline of a circle, negative inside.
+pub fn dist_to_circle(center: Vector2, radius: f32, other: Vector2) -> f32 {
+    other.dist(&center) - radius
+}
+
+/// Approximate signed distance to the outline of an axis-aligned ellipse, negative inside.
+/// Exact on the axes and close enough near the outline for anti-aliasing.
+pub fn dist_to_ellipse(center: Vector2, radii: Vector2, other: Vector2) -> f32 {
+    let p = other - center;
+    let k0 = Vector2::new(p.x / radii.x, p.y / radii.y).dist(&Vector2::new(0.0, 0.0));
+    let k1 = Vector2::new(p.x / (radii.x * radii.x), p.y / (radii.y * radii.y)).dist(&Vector2::new(0.0, 0.0));
+    if k1 == 0.0 {
+        return -radii.x.min(radii.y);
+    }
+    k0 * (k0 - 1.0) / k1
+}
+
+/// Signed distance to the outline of a rect with rounded corners, negative inside.
+/// Radii are limited to half the shorter side, a negative size extends the rect from `pos` the other way.
+pub fn dist_to_rounded_rect(pos: Vector2, size: Vector2, radii: CornerRadii, other: Vector2) -> f32 {
+    let p = other - (pos + size * 0.5);
+    let half = Vector2::new(size.x.abs() / 2.0, size.y.abs() / 2.0);
+    let r = match (p.x > 0.0, p.y > 0.0) {
+        (false, false) => radii.top_left,
+        (true, false) => radii.top_right,
+        (true, true) => radii.bottom_right,
+        (false, true) => radii.bottom_left,
+    }.clamp(0.0, half.x.min(half.y));
+
+    let q = Vector2::new(p.x.abs() - half.x + r, p.y.abs() - half.y + r);
+    let outside = Vector2::new(q.x.max(0.0), q.y.max(0.0)).dist(&Vector2::new(0.0, 0.0));
+    q.x.max(q.y).min(0.0) + outside - r
+}
@@ -0,0 +1,106 @@
+use crate::{path::FillRule, pixel_util::{Rect, Vector2}};
+
+/// Scanlines sampled per pixel row, horizontal coverage is computed exactly.
+const SUBSAMPLES: usize = 5;
+
+/// Per-pixel coverage of a shape within `area`, row by row.
+pub(crate) struct Mask {
+    pub area: Rect,
+    pub coverage: Vec<f32>,
+}
+
+impl Mask {
+    pub fn get(&self, x: i32, y: i32) -> f32 {
+        self.coverage[((y - self.area.y) * self.area.width + x - self.area.x) as usize]
+    }
+}
+
+struct Edge {
+    top: Vector2,
+    bottom: Vector2,
+    winding: i32,
+}
+
+/// Scan converts closed polygons into an anti-aliased coverage mask limited to `clip`.
+pub(crate) fn rasterize(polygons: &[Vec<Vector2>], rule: FillRule, clip: Rect) -> Mask {
+    let mut edges = vec![];
+    let mut min = Vector2::new(f32::INFINITY, f32::INFINITY);
+    let mut max = Vector2::new(f32::NEG_INFINITY, f32::NEG_INFINITY);
+    for polygon in polygons {
+        for (i, &a) in polygon.iter().enumerate() {
+            let b = polygon[(i + 1) % polygon.len()];
+            min = Vector2::new(min.x.min(a.x), min.y.min(a.y));
+            max = Vector2::new(max.x.max(a.x), max.y.max(a.y));
+            if a.y == b.y {
+                continue;
+            }
+            edges.push(if a.y < b.y {
+                Edge {top: a, bottom: b, winding: 1}
+            } else {
+                Edge {top: b, bottom: a, winding: -1}
+            });
+        }
+    }
+
+    if edges.is_empty() {
+        return Mask {area: Rect::new(0, 0, 0, 0), coverage: vec![]};
+    }
+    let area = Rect::from_bounds(min, max).intersection(&clip);
+    let width = area.width as usize;
+    let mut coverage = vec![0.0; width * area.height as usize];
+    let weight = 1.0 / SUBSAMPLES as f32;
+    let mut crossings: Vec<(f32, i32)> = vec![];
+
+    for row in 0..area.height as usize {
+        let cells = &mut coverage[row * width..(row + 1) * width];
+        for sub in 0..SUBSAMPLES {
+            let y = (area.y + row as i32) as f32 + (sub as f32 + 0.5) * weight;
+            crossings.clear();
+            for edge in &edges {
+                if y >= edge.top.y && y < edge.bottom.y {
+                    let x = edge.top.x + (y - edge.top.y) * (edge.bottom.x - edge.top.x) / (edge.bottom.y - edge.top.y);
+                    crossings.push((x - area.x as f32, edge.winding));
+                }
+            }
+            crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+            let mut winding = 0;
+            for i in 0..crossings.len().saturating_sub(1) {
+                winding += crossings[i].1;
+                let inside = match rule {
+                    FillRule::NonZero => winding != 0,
+                    FillRule::EvenOdd => winding % 2 != 0,
+                };
+                if inside {
+                    add_span(cells, crossings[i].0, crossings[i + 1].0, weight);
+                }
+            }
+        }
+    }
+
+    for c in coverage.iter_mut() {
+        *c = c.min(1.0);
+    }
+    Mask {area, coverage}
+}
+
+/// Adds `weight` to the cells between `x0` and `x1`, partially covered cells get their share.
+fn add_span(cells: &mut [f32], x0: f32, x1: f32, weight: f32) {
+    let len = cells.len() as f32;
+    let (x0, x1) = (x0.clamp(0.0, len), x1.clamp(0.0, len));
+    if x1 <= x0 {
+        return;
+    }
+    let (i0, i1) = (x0.floor() as usize, x1.floor() as usize);
+    if i0 == i1 {
+        cells[i0] += (x1 - x0) * weight;
+        return;
+    }
+    cells[i0] += (i0 as f32 + 1.0 - x0) * weight;
+    for cell in &mut cells[i0 + 1..i1] {
+        *cell += weight;
+    }
+    if i1 < cells.len() {
+        cells[i1] += (x1 - i1 as f32) * weight;
+    }
+}
@@ -0,0 +1,133 @@
+use std::{fs::File, os::{fd::AsFd, unix::fs::FileExt}, sync::{atomic::{AtomicBool, Ordering}, Arc}};
+
+use tempfile::tempfile;
+use wayland_client::{protocol::{wl_buffer::WlBuffer, wl_shm::{Format, WlShm}, wl_shm_pool::WlShmPool}, QueueHandle};
+
+use crate::{color::PixelFormat, widget::{WidgetData, WidgetError}};
+
+/// Buffers allocated up front, enough for triple buffering.
+const INITIAL_SLOTS: usize = 3;
+/// The pool grows past `INITIAL_SLOTS` only while the compositor holds every buffer, and never beyond this.
+const MAX_SLOTS: usize = 4;
+
+impl From<PixelFormat> for Format {
+    fn from(value: PixelFormat) -> Self {
+        match value {
+            PixelFormat::Argb8888 => Format::Argb8888,
+            PixelFormat::Xrgb8888 => Format::Xrgb8888,
+            PixelFormat::Argb2101010 => Format::Argb2101010,
+            PixelFormat::Xrgb2101010 => Format::Xrgb2101010,
+        }
+    }
+}
+
+struct Slot {
+    buffer: WlBuffer,
+    busy: Arc<AtomicBool>,
+    offset: u64,
+}
+
+/// A single shm file split into equally sized `WlBuffer`s.
+///
+/// A buffer is marked busy when it is attached to the surface and becomes
+/// available again once the compositor sends `wl_buffer.release`.
+pub struct BufferPool {
+    file: File,
+    pool: WlShmPool,
+    slots: Vec<Slot>,
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    /// Pixels converted from the canvas' storage format, reused between frames
+    converted: Vec<u8>,
+}
+
+impl BufferPool {
+    pub fn new(shm: &WlShm, width: u32, height: u32, format: PixelFormat, qh: &QueueHandle<WidgetData>) -> Result<Self, WidgetError> {
+        let file = tempfile()?;
+        let size = Self::slot_size_for(width, height) * INITIAL_SLOTS;
+        file.set_len(size as u64)?;
+        let pool = shm.create_pool(file.as_fd(), size as i32, qh, ());
+
+        let mut pool = Self {file, pool, slots: vec![], width, height, format, converted: vec![]};
+        for _ in 0..INITIAL_SLOTS {
+            pool.push_slot(qh);
+        }
+        Ok(pool)
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn format(&self) -> Format {
+        self.format.into()
+    }
+
+    fn slot_size_for(width: u32, height: u32) -> usize {
+        width as usize * height as usize * 4
+    }
+
+    fn slot_size(&self) -> usize {
+        Self::slot_size_for(self.width, self.height)
+    }
+
+    fn push_slot(&mut self, qh: &QueueHandle<WidgetData>) {
+        let offset = (self.slots.len() * self.slot_size()) as u64;
+        let busy = Arc::new(AtomicBool::new(false));
+        let buffer = self.pool.create_buffer(offset as i32, self.width as i32, self.height as i32, self.width as i32 * 4, self.format.into(), qh, busy.clone());
+        self.slots.push(Slot {buffer, busy, offset});
+    }
+
+    fn grow(&mut self, qh: &QueueHandle<WidgetData>) -> Result<(), WidgetError> {
+        let size = self.slot_size() * (self.slots.len() + 1);
+        self.file.set_len(size as u64)?;
+        self.pool.resize(size as i32);
+        self.push_slot(qh);
+        Ok(())
+    }
+
+    /// Returns the index of a buffer the compositor is not using, growing the pool if needed.
+    /// `None` means every buffer is busy and the pool is already at its maximum size.
+    pub fn acquire(&mut self, qh: &QueueHandle<WidgetData>) -> Result<Option<usize>, WidgetError> {
+        if let Some(index) = self.slots.iter().position(|s| !s.busy.load(Ordering::Acquire)) {
+            return Ok(Some(index));
+        }
+        if self.slots.len() < MAX_SLOTS {
+            self.grow(qh)?;
+            return Ok(Some(self.slots.len() - 1));
+        }
+        Ok(None)
+    }
+
+    /// Copies `pixels` into the buffer at `index` and marks it busy until the compositor releases it.
+    /// `pixels` are in the [`PixelFormat::storage`] of the pool's format and converted if it differs.
+    pub fn write(&mut self, index: usize, pixels: &[u8]) -> Result<&WlBuffer, WidgetError> {
+        let pixels = &pixels[..self.slot_size()];
+        let (storage, format) = (self.format.storage(), self.format);
+        let pixels = if storage == format {
+            pixels
+        } else {
+            self.converted.clear();
+            self.converted.extend(pixels.chunks_exact(4).flat_map(|p| format.encode(storage.decode([p[0], p[1], p[2], p[3]]))));
+            &self.converted
+        };
+        let slot = &self.slots[index];
+        self.file.write_all_at(pixels, slot.offset)?;
+        slot.busy.store(true, Ordering::Release);
+        Ok(&slot.buffer)
+    }
+}
+
+impl Drop for BufferPool {
+    fn drop(&mut self) {
+        for slot in &self.slots {
+            slot.buffer.destroy();
+        }
+        self.pool.destroy();
+    }
+}
@@ -0,0 +1,263 @@
+use crate::{canvas::Canvas, paint::Paint, path::{circle_polygon, signed_area, FillRule, Path, Polyline}, pixel_util::Vector2};
+
+/// Shape drawn past the ends of open subpaths and dashes.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum LineCap {
+    /// Ends exactly at the end point
+    Butt,
+    /// Extends past the end point by half the width
+    Square,
+    #[default]
+    Round,
+}
+
+/// Shape drawn where two segments meet.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum LineJoin {
+    /// Extends the outer edges until they meet, falls back to a bevel past the miter limit
+    Miter,
+    Bevel,
+    #[default]
+    Round,
+}
+
+/// How lines, arcs and paths are stroked.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StrokeStyle {
+    width: f32,
+    cap: LineCap,
+    join: LineJoin,
+    miter_limit: f32,
+    dash: Vec<f32>,
+    dash_offset: f32,
+}
+
+impl StrokeStyle {
+    /// `width` is the full width of the stroke, centred on the path.
+    pub fn new(width: f32) -> Self {
+        Self {width, cap: LineCap::default(), join: LineJoin::default(), miter_limit: 4.0, dash: vec![], dash_offset: 0.0}
+    }
+
+    pub fn cap(mut self, cap: LineCap) -> Self {
+        self.cap = cap;
+        self
+    }
+
+    pub fn join(mut self, join: LineJoin) -> Self {
+        self.join = join;
+        self
+    }
+
+    /// Longest miter allowed, as a multiple of the width.
+    pub fn miter_limit(mut self, limit: f32) -> Self {
+        self.miter_limit = limit;
+        self
+    }
+
+    /// Alternating lengths of dashes and gaps, starting `offset` into the pattern.
+    /// An odd number of lengths is repeated to make it even, like in SVG.
+    pub fn dash(mut self, pattern: Vec<f32>, offset: f32) -> Self {
+        self.dash = pattern;
+        self.dash_offset = offset;
+        self
+    }
+
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    /// Turns the outline of a stroke into polygons that all wind the same way,
+    /// so filling them with [`FillRule::NonZero`] unions them without double blending.
+    pub(crate) fn polygons(&self, lines: &[Polyline]) -> Vec<Vec<Vector2>> {
+        let half = self.width / 2.0;
+        let mut polygons = vec![];
+        let mut push = |mut polygon: Vec<Vector2>| {
+            if signed_area(&polygon) < 0.0 {
+                polygon.reverse();
+            }
+            polygons.push(polygon);
+        };
+
+        let dashed;
+        let lines = if self.dash.iter().sum::<f32>() > 0.0 && self.dash.iter().all(|d| *d >= 0.0) {
+            dashed = dash(lines, &self.dash, self.dash_offset);
+            &dashed
+        } else {
+            lines
+        };
+
+        for line in lines {
+            let mut points: Vec<Vector2> = line.points.clone();
+            points.dedup_by(|a, b| a.dist(b) < 1e-4);
+            if line.closed && points.len() > 1 && points[0].dist(points.last().unwrap()) < 1e-4 {
+                points.pop();
+            }
+
+            if points.len() == 1 {
+                // A zero length line only shows its caps
+                let p = points[0];
+                match self.cap {
+                    LineCap::Butt => {},
+                    LineCap::Square => push(vec![p + Vector2::new(-half, -half), p + Vector2::new(half, -half), p + Vector2::new(half, half), p + Vector2::new(-half, half)]),
+                    LineCap::Round => push(circle_polygon(p, half)),
+                }
+                continue;
+            }
+
+            let closed = line.closed && points.len() > 2;
+            let segment_count = if closed { points.len() } else { points.len() - 1 };
+            for i in 0..segment_count {
+                let (a, b) = (points[i], points[(i + 1) % points.len()]);
+                let n = normal(a, b) * half;
+                push(vec![a + n, b + n, b - n, a - n]);
+            }
+
+            let joints = if closed { 0..points.len() } else { 1..points.len() - 1 };
+            for i in joints {
+                let prev = points[(i + points.len() - 1) % points.len()];
+                self.join_polygon(prev, points[i], points[(i + 1) % points.len()], half).map(&mut push);
+            }
+
+            if !closed {
+                let last = points.len() - 1;
+                self.cap_polygon(points[1], points[0], half).map(&mut push);
+                self.cap_polygon(points[last - 1], points[last], half).map(&mut push);
+            }
+        }
+        polygons
+    }
+
+    /// The wedge filling the outer gap where the segment `a`-`p` turns towards `b`.
+    fn join_polygon(&self, a: Vector2, p: Vector2, b: Vector2, half: f32) -> Option<Vec<Vector2>> {
+        let (d0, d1) = (direction(a, p), direction(p, b));
+        let cross = d0.x * d1.y - d0.y * d1.x;
+        let dot = d0.x * d1.x + d0.y * d1.y;
+        if cross.abs() < 1e-6 && dot > 0.0 {
+            return None;
+        }
+        if self.join == LineJoin::Round {
+            return Some(circle_polygon(p, half));
+        }
+
+        // The outer side is the one the path turns away from
+        let side = if cross > 0.0 { -half } else { half };
+        let (n0, n1) = (normal(a, p) * side, normal(p, b) * side);
+        if self.join == LineJoin::Miter {
+            let cos_half = ((1.0 + dot) / 2.0).sqrt();
+            if cos_half > 0.0 && 1.0 / cos_half <= self.miter_limit {
+                let bisector = n0 + n1;
+                let len = bisector.dist(&Vector2::new(0.0, 0.0));
+                let miter = bisector * (half / cos_half / len);
+                return Some(vec![p, p + n0, p + miter, p + n1]);
+            }
+        }
+        Some(vec![p, p + n0, p + n1])
+    }
+
+    /// The cap past `end`, for a line coming from `from`.
+    fn cap_polygon(&self, from: Vector2, end: Vector2, half: f32) -> Option<Vec<Vector2>> {
+        match self.cap {
+            LineCap::Butt => None,
+            LineCap::Round => Some(circle_polygon(end, half)),
+            LineCap::Square => {
+                let n = normal(from, end) * half;
+                let d = direction(from, end) * half;
+                Some(vec![end + n, end + n + d, end - n + d, end - n])
+            },
+        }
+    }
+}
+
+/// A plain stroke `value` wide in total, unlike the thickness of [`Canvas::draw_line`] which is measured from the centre.
+impl From<f32> for StrokeStyle {
+    fn from(value: f32) -> Self {
+        Self::new(value)
+    }
+}
+
+fn direction(a: Vector2, b: Vector2) -> Vector2 {
+    let len = a.dist(&b);
+    if len == 0.0 {
+        return Vector2::new(1.0, 0.0);
+    }
+    (b - a) * (1.0 / len)
+}
+
+fn normal(a: Vector2, b: Vector2) -> Vector2 {
+    let d = direction(a, b);
+    Vector2::new(-d.y, d.x)
+}
+
+/// Splits polylines into the open pieces covered by dashes.
+fn dash(lines: &[Polyline], pattern: &[f32], offset: f32) -> Vec<Polyline> {
+    let pattern: Vec<f32> = if pattern.len() % 2 == 1 { pattern.repeat(2) } else { pattern.to_vec() };
+    let total: f32 = pattern.iter().sum();
+    let mut dashes = vec![];
+
+    for line in lines {
+        let mut points = line.points.clone();
+        if line.closed {
+            points.push(points[0]);
+        }
+
+        // Every subpath starts over at the offset
+        let mut index = 0;
+        let mut remaining = pattern[0];
+        let mut skip = offset.rem_euclid(total);
+        while skip > 0.0 {
+            if skip < remaining {
+                remaining -= skip;
+                break;
+            }
+            skip -= remaining;
+            index = (index + 1) % pattern.len();
+            remaining = pattern[index];
+        }
+
+        let mut current = if index % 2 == 0 { vec![points[0]] } else { vec![] };
+        for pair in points.windows(2) {
+            let (mut a, b) = (pair[0], pair[1]);
+            let mut len = a.dist(&b);
+            while len > remaining {
+                a = a + direction(a, b) * remaining;
+                len -= remaining;
+                if index % 2 == 0 {
+                    current.push(a);
+                    dashes.push(Polyline {points: std::mem::take(&mut current), closed: false});
+                } else {
+                    current.push(a);
+                }
+                index = (index + 1) % pattern.len();
+                remaining = pattern[index];
+            }
+            remaining -= len;
+            if index % 2 == 0 {
+                current.push(b);
+            }
+        }
+        if index % 2 == 0 && !current.is_empty() {
+            dashes.push(Polyline {points: current, closed: false});
+        }
+    }
+    dashes
+}
+
+impl Canvas {
+    /// Strokes every subpath of `path`, the width is centred on the path.
+    pub fn stroke_path<S: Into<StrokeStyle>>(&mut self, paint: Paint, path: &Path, style: S) {
+        let polygons = style.into().polygons(&path.flatten_with_tolerance(self.local_tolerance()));
+        self.fill_polygons(&paint, &polygons, FillRule::NonZero);
+    }
+
+    /// Strokes the segment from `a` to `b` with caps and dashes from `style`.
+    ///
+    /// The style's width is the full width, so `stroke_line(.., 4.0)` covers as much as `draw_line(.., 2.0)`.
+    pub fn stroke_line<S: Into<StrokeStyle>>(&mut self, paint: Paint, a: Vector2, b: Vector2, style: S) {
+        self.stroke_path(paint, &Path::new().move_to(a).line_to(b), style)
+    }
+
+    /// Strokes the arc from `start` to `end` with caps and dashes from `style`, the width is the full width.
+    pub fn stroke_arc<S: Into<StrokeStyle>>(&mut self, paint: Paint, center: Vector2, radius: f32, start: f32, end: f32, style: S) {
+        self.stroke_path(paint, &Path::new().arc_to(center, radius, start, end), style)
+    }
+}
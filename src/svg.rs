@@ -0,0 +1,160 @@
+use std::path::Path as FilePath;
+
+use usvg::tiny_skia_path::PathSegment;
+
+use crate::{canvas::Canvas, color::{BlendMode, Color}, paint::{ExtendMode, Gradient, Paint}, path::{FillRule, Path}, pixel_util::Vector2, stroke::{LineCap, LineJoin, StrokeStyle}, transform::Transform, widget::WidgetError};
+
+/// A parsed SVG document, drawn with [`Canvas::draw_svg`].
+///
+/// Shapes, solid colours, linear and radial gradients and strokes are supported.
+/// Text, embedded images, patterns, clip paths, masks and filters are skipped,
+/// icon sets rarely rely on them.
+#[derive(Clone)]
+pub struct Svg {
+    tree: usvg::Tree,
+}
+
+impl Svg {
+    pub fn parse(text: &str) -> Result<Self, WidgetError> {
+        Ok(Self {tree: usvg::Tree::from_str(text, &usvg::Options::default())?})
+    }
+
+    /// Parses an SVG or gzip compressed SVGZ document.
+    pub fn from_data(data: &[u8]) -> Result<Self, WidgetError> {
+        Ok(Self {tree: usvg::Tree::from_data(data, &usvg::Options::default())?})
+    }
+
+    pub fn open<P: AsRef<FilePath>>(path: P) -> Result<Self, WidgetError> {
+        Self::from_data(&std::fs::read(path)?)
+    }
+
+    /// The size the document asks for, in pixels.
+    pub fn size(&self) -> Vector2 {
+        Vector2::new(self.tree.size().width(), self.tree.size().height())
+    }
+}
+
+impl From<usvg::Transform> for Transform {
+    fn from(value: usvg::Transform) -> Self {
+        Self::new(value.sx, value.ky, value.kx, value.sy, value.tx, value.ty)
+    }
+}
+
+fn convert_path(data: &usvg::tiny_skia_path::Path) -> Path {
+    let v = |p: usvg::tiny_skia_path::Point| Vector2::new(p.x, p.y);
+    data.segments().fold(Path::new(), |path, segment| match segment {
+        PathSegment::MoveTo(p) => path.move_to(v(p)),
+        PathSegment::LineTo(p) => path.line_to(v(p)),
+        PathSegment::QuadTo(c, p) => path.quad_to(v(c), v(p)),
+        PathSegment::CubicTo(c1, c2, p) => path.cubic_to(v(c1), v(c2), v(p)),
+        PathSegment::Close => path.close(),
+    })
+}
+
+/// Converts an SVG paint, replacing its colours with `recolor` but keeping their opacity.
+fn convert_paint(paint: &usvg::Paint, opacity: f32, recolor: Option<Color>) -> Option<Paint> {
+    let color = |c: usvg::Color, alpha: f32| match recolor {
+        Some(r) => Color::new(r.r, r.g, r.b, (r.a as f32 * alpha).round() as u8),
+        None => Color::new(c.red, c.green, c.blue, (255.0 * alpha).round() as u8),
+    };
+    let gradient = |base: &usvg::BaseGradient| {
+        let stops = base.stops().iter().fold(Gradient::new(vec![]), |g, s| g.stop(s.offset().get(), color(s.color(), s.opacity().get() * opacity)));
+        stops.extend(match base.spread_method() {
+            usvg::SpreadMethod::Pad => ExtendMode::Pad,
+            usvg::SpreadMethod::Reflect => ExtendMode::Reflect,
+            usvg::SpreadMethod::Repeat => ExtendMode::Repeat,
+        })
+    };
+
+    // Gradient transforms are applied to the gradient's geometry, which is exact
+    // for translations, rotations and uniform scaling
+    match paint {
+        usvg::Paint::Color(c) => Some(Paint::Solid(color(*c, opacity))),
+        usvg::Paint::LinearGradient(g) => {
+            let t = Transform::from(g.transform());
+            Some(Paint::linear(t.apply(Vector2::new(g.x1(), g.y1())), t.apply(Vector2::new(g.x2(), g.y2())), gradient(g)))
+        },
+        usvg::Paint::RadialGradient(g) => {
+            let t = Transform::from(g.transform());
+            Some(Paint::radial(t.apply(Vector2::new(g.cx(), g.cy())), g.r().get() * t.scale_factor(), gradient(g)))
+        },
+        usvg::Paint::Pattern(_) => None,
+    }
+}
+
+impl Canvas {
+    /// Draws `svg` stretched over the rect at `pos` with `size`.
+    ///
+    /// `recolor` replaces every colour while keeping its opacity, for symbolic icons
+    /// that are meant to take the colour of the surrounding text.
+    pub fn draw_svg(&mut self, svg: &Svg, pos: Vector2, size: Vector2, recolor: Option<Color>) {
+        let document = svg.size();
+        self.save();
+        self.transform(Transform::scaling(size.x / document.x, size.y / document.y).then(&Transform::translation(pos.x, pos.y)));
+        let base = self.current_transform();
+        self.draw_svg_group(svg.tree.root(), base, recolor);
+        self.restore();
+    }
+
+    /// Translucent groups are drawn into a layer, so their children fade together.
+    fn draw_svg_group(&mut self, group: &usvg::Group, base: Transform, recolor: Option<Color>) {
+        let opacity = group.opacity().get();
+        if opacity < 1.0 {
+            self.push_layer(opacity, BlendMode::SourceOver, None);
+        }
+        for node in group.children() {
+            match node {
+                usvg::Node::Group(group) => self.draw_svg_group(group, base, recolor),
+                usvg::Node::Path(path) if path.is_visible() => self.draw_svg_path(path, base, recolor),
+                _ => {},
+            }
+        }
+        if opacity < 1.0 {
+            self.pop_layer();
+        }
+    }
+
+    fn draw_svg_path(&mut self, node: &usvg::Path, base: Transform, recolor: Option<Color>) {
+        self.set_transform(Transform::from(node.abs_transform()).then(&base));
+        let path = convert_path(node.data());
+
+        let fill = node.fill().and_then(|fill| {
+            let rule = match fill.rule() {
+                usvg::FillRule::NonZero => FillRule::NonZero,
+                usvg::FillRule::EvenOdd => FillRule::EvenOdd,
+            };
+            Some((convert_paint(fill.paint(), fill.opacity().get(), recolor)?, rule))
+        });
+        let stroke = node.stroke().and_then(|stroke| {
+            let style = StrokeStyle::new(stroke.width().get())
+                .cap(match stroke.linecap() {
+                    usvg::LineCap::Butt => LineCap::Butt,
+                    usvg::LineCap::Round => LineCap::Round,
+                    usvg::LineCap::Square => LineCap::Square,
+                })
+                .join(match stroke.linejoin() {
+                    usvg::LineJoin::Miter | usvg::LineJoin::MiterClip => LineJoin::Miter,
+                    usvg::LineJoin::Round => LineJoin::Round,
+                    usvg::LineJoin::Bevel => LineJoin::Bevel,
+                })
+                .miter_limit(stroke.miterlimit().get())
+                .dash(stroke.dasharray().map(|d| d.to_vec()).unwrap_or_default(), stroke.dashoffset());
+            Some((convert_paint(stroke.paint(), stroke.opacity().get(), recolor)?, style))
+        });
+
+        let stroke_first = node.paint_order() == usvg::PaintOrder::StrokeAndFill;
+        if stroke_first {
+            if let Some((paint, style)) = stroke.clone() {
+                self.stroke_path(paint, &path, style);
+            }
+        }
+        if let Some((paint, rule)) = fill {
+            self.fill_path(paint, &path, rule);
+        }
+        if !stroke_first {
+            if let Some((paint, style)) = stroke {
+                self.stroke_path(paint, &path, style);
+            }
+        }
+    }
+}
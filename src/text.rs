@@ -0,0 +1,238 @@
+use std::ops::Range;
+
+use rusttype::{point, Scale};
+
+use crate::{canvas::Canvas, color::Color, font::FontSet, paint::Paint, pixel_util::Vector2};
+
+/// The size of a string set by [`Canvas::draw_text`](crate::canvas::Canvas::draw_text), relative to the start of its baseline.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TextMetrics {
+    /// How far the pen moves, i.e. where text drawn after this string would start
+    pub width: f32,
+    /// Top left and bottom right of the inked pixels, `None` when nothing is drawn
+    pub bounds: Option<(Vector2, Vector2)>,
+    /// How far the font reaches above the baseline
+    pub ascent: f32,
+    /// How far the font reaches below the baseline, positive
+    pub descent: f32,
+    /// Extra space the font asks for between lines
+    pub line_gap: f32,
+}
+
+impl TextMetrics {
+    /// Height of a line without the gap, the same for every string in a font.
+    pub fn height(&self) -> f32 {
+        self.ascent + self.descent
+    }
+
+    /// Distance between the baselines of consecutive lines.
+    pub fn line_height(&self) -> f32 {
+        self.ascent + self.descent + self.line_gap
+    }
+}
+
+/// Measures `text` as it would be drawn with `font` at `size`, including kerning and fallback fonts.
+pub fn measure_text<'f, S: Into<FontSet<'f>>>(text: &str, font: S, size: f32) -> TextMetrics {
+    let fonts = font.into();
+    let scale = Scale::uniform(size);
+    let v_metrics = fonts.v_metrics(scale);
+    let mut width: f32 = 0.0;
+    let mut bounds: Option<(Vector2, Vector2)> = None;
+
+    for (_, glyph) in fonts.layout(text, scale, point(0.0, 0.0)) {
+        width = width.max(glyph.position().x + glyph.unpositioned().h_metrics().advance_width);
+        if let Some(bb) = glyph.unpositioned().exact_bounding_box() {
+            let offset = glyph.position();
+            let (min, max) = (Vector2::new(bb.min.x + offset.x, bb.min.y), Vector2::new(bb.max.x + offset.x, bb.max.y));
+            bounds = Some(bounds.map_or((min, max), |(a, b)| {
+                (Vector2::new(a.x.min(min.x), a.y.min(min.y)), Vector2::new(b.x.max(max.x), b.y.max(max.y)))
+            }));
+        }
+    }
+
+    TextMetrics {width, bounds, ascent: v_metrics.ascent, descent: -v_metrics.descent, line_gap: v_metrics.line_gap}
+}
+
+/// Where lines break when they don't fit the maximum width.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Wrap {
+    /// Between words, words longer than a line are broken between characters
+    #[default]
+    Word,
+    /// Between any two characters
+    Character,
+}
+
+/// How lines are placed within the width of a paragraph.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Align {
+    #[default]
+    Left,
+    Center,
+    Right,
+    /// Stretches the spaces of wrapped lines to fill the width, the last line of a paragraph stays left aligned
+    Justify,
+}
+
+/// A line of a laid out paragraph.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Line {
+    /// Byte range of the line in the text, without the spaces or newline it was broken at
+    pub range: Range<usize>,
+    /// Start of the baseline relative to the top left of the paragraph
+    pub pos: Vector2,
+    /// Width of the text, without any justification
+    pub width: f32,
+    /// Extra space added to every space in the line when justified
+    pub word_spacing: f32,
+}
+
+/// Breaks text into lines at newlines and, with a maximum width, wherever it doesn't fit.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextLayout {
+    max_width: Option<f32>,
+    wrap: Wrap,
+    align: Align,
+    line_height: f32,
+}
+
+impl Default for TextLayout {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TextLayout {
+    pub fn new() -> Self {
+        Self {max_width: None, wrap: Wrap::default(), align: Align::default(), line_height: 1.0}
+    }
+
+    /// Wraps lines wider than `width`, without one lines only break at newlines.
+    /// Negative widths wrap after every character, NaN doesn't wrap.
+    pub fn max_width(mut self, width: f32) -> Self {
+        self.max_width = (!width.is_nan()).then(|| width.max(0.0));
+        self
+    }
+
+    pub fn wrap(mut self, wrap: Wrap) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    pub fn align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+
+    /// Distance between baselines as a multiple of the font's line height, 1.0 by default.
+    pub fn line_height(mut self, factor: f32) -> Self {
+        self.line_height = factor;
+        self
+    }
+
+    /// Lays out `text` set with `font` at `size`.
+    pub fn lines<'f, S: Into<FontSet<'f>>>(&self, text: &str, font: S, size: f32) -> Vec<Line> {
+        let fonts = font.into();
+        let metrics = measure_text("", &fonts, size);
+        let width = |range: &Range<usize>| measure_text(&text[range.clone()], &fonts, size).width;
+
+        // Ranges of each line and whether it was wrapped rather than ending its paragraph
+        let mut breaks: Vec<(Range<usize>, bool)> = vec![];
+        let mut start = 0;
+        for paragraph in text.split('\n') {
+            let end = start + paragraph.len();
+            match self.max_width {
+                Some(max) => self.wrap_paragraph(start..end, text, max, &width, &mut breaks),
+                None => breaks.push((start..end, false)),
+            }
+            start = end + 1;
+        }
+
+        let widths: Vec<f32> = breaks.iter().map(|(range, _)| width(range)).collect();
+        let box_width = self.max_width.unwrap_or_else(|| widths.iter().copied().fold(0.0, f32::max));
+        let advance = metrics.line_height() * self.line_height;
+
+        breaks.into_iter().zip(widths).enumerate().map(|(i, ((range, wrapped), width))| {
+            let spaces = text[range.clone()].chars().filter(|&c| c == ' ').count();
+            let free = (box_width - width).max(0.0);
+            let (x, word_spacing) = match self.align {
+                Align::Left => (0.0, 0.0),
+                Align::Center => (free / 2.0, 0.0),
+                Align::Right => (free, 0.0),
+                Align::Justify if wrapped && spaces > 0 => (0.0, free / spaces as f32),
+                Align::Justify => (0.0, 0.0),
+            };
+            Line {range, pos: Vector2::new(x, metrics.ascent + i as f32 * advance), width, word_spacing}
+        }).collect()
+    }
+
+    /// Width and height of the laid out text, the width is the maximum width when there is one.
+    pub fn measure<'f, S: Into<FontSet<'f>>>(&self, text: &str, font: S, size: f32) -> Vector2 {
+        let fonts = font.into();
+        let metrics = measure_text("", &fonts, size);
+        let lines = self.lines(text, &fonts, size);
+        let width = self.max_width.unwrap_or_else(|| lines.iter().map(|l| l.width).fold(0.0, f32::max));
+        let height = match lines.last() {
+            Some(last) => last.pos.y + metrics.descent,
+            None => 0.0,
+        };
+        Vector2::new(width, height)
+    }
+
+    fn wrap_paragraph<W: Fn(&Range<usize>) -> f32>(&self, paragraph: Range<usize>, text: &str, max: f32, width: &W, breaks: &mut Vec<(Range<usize>, bool)>) {
+        let mut start = paragraph.start;
+        loop {
+            // Spaces a line was broken at don't start the next one
+            start += text[start..paragraph.end].len() - text[start..paragraph.end].trim_start_matches(' ').len();
+            if start >= paragraph.end || width(&(start..paragraph.end)) <= max {
+                breaks.push((start..paragraph.end, false));
+                return;
+            }
+            let chars: Vec<usize> = text[start..paragraph.end].char_indices().map(|(i, _)| start + i).skip(1).collect();
+            // The longest prefix that fits, always at least one character so layout makes progress
+            let fits = chars.iter().take_while(|&&end| width(&(start..end)) <= max).last().copied()
+                .unwrap_or_else(|| chars.first().copied().unwrap_or(paragraph.end));
+            let end = match self.wrap {
+                Wrap::Word if text[fits..].starts_with(' ') => fits,
+                Wrap::Word => text[start..fits].rfind(' ').map(|i| start + i).filter(|&i| i > start).unwrap_or(fits),
+                Wrap::Character => fits,
+            };
+            let trimmed = start + text[start..end].trim_end_matches(' ').len();
+            // A last character too wide for the line ends the paragraph rather than leaving an empty line
+            breaks.push((start..trimmed, end < paragraph.end));
+            if end >= paragraph.end {
+                return;
+            }
+            start = end;
+        }
+    }
+}
+
+impl Canvas {
+    /// Draws `text` laid out by `layout` with the top left corner of the paragraph at `pos`.
+    ///
+    /// `colorf` gets the index of each character in the whole text, like [`Canvas::draw_text`],
+    /// glyphs are blended over the existing pixels without a background.
+    pub fn draw_paragraph<'f, S: Into<FontSet<'f>>, F: Fn(u32, char) -> Paint>(&mut self, text: &str, pos: Vector2, size: f32, font: S, layout: &TextLayout, colorf: F) {
+        let fonts = font.into();
+        let bg: Paint = Color::TRANSPARENT.into();
+        for line in layout.lines(text, &fonts, size) {
+            let offset = text[..line.range.start].chars().count() as u32;
+            let origin = pos + line.pos;
+            let line_text = &text[line.range];
+            if line.word_spacing == 0.0 {
+                self.draw_text(line_text.into(), origin, size, &fonts, |i, c| colorf(offset + i, c), bg.clone());
+                continue;
+            }
+            // Justified lines are drawn a word at a time so the spaces can stretch
+            let words = line_text.split(' ').scan(0, |at, word| { let start = *at; *at += word.len() + 1; Some((start, word)) });
+            for (spaces, (start, word)) in words.enumerate() {
+                if !word.is_empty() {
+                    let x = measure_text(&line_text[..start], &fonts, size).width + spaces as f32 * line.word_spacing;
+                    let offset = offset + line_text[..start].chars().count() as u32;
+                    self.draw_text(word.into(), origin + Vector2::new(x, 0.0), size, &fonts, |i, c| colorf(offset + i, c), bg.clone());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use crate::{canvas::Canvas, pixel_util::Vector2};
+
+/// A 2D affine transform, mapping `(x, y)` to `(a * x + c * y + e, b * x + d * y + f)`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transform {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub e: f32,
+    pub f: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform {a: 1.0, b: 0.0, c: 0.0, d: 1.0, e: 0.0, f: 0.0};
+
+    pub fn new(a: f32, b: f32, c: f32, d: f32, e: f32, f: f32) -> Self {
+        Self {a, b, c, d, e, f}
+    }
+
+    pub fn translation(x: f32, y: f32) -> Self {
+        Self::new(1.0, 0.0, 0.0, 1.0, x, y)
+    }
+
+    pub fn scaling(x: f32, y: f32) -> Self {
+        Self::new(x, 0.0, 0.0, y, 0.0, 0.0)
+    }
+
+    /// Rotates by `angle` radians, clockwise on screen like [`Canvas::draw_arc`].
+    pub fn rotation(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::new(cos, sin, -sin, cos, 0.0, 0.0)
+    }
+
+    /// The transform applying `self` first and `other` after it.
+    pub fn then(&self, other: &Transform) -> Self {
+        Self {
+            a: other.a * self.a + other.c * self.b,
+            b: other.b * self.a + other.d * self.b,
+            c: other.a * self.c + other.c * self.d,
+            d: other.b * self.c + other.d * self.d,
+            e: other.a * self.e + other.c * self.f + other.e,
+            f: other.b * self.e + other.d * self.f + other.f,
+        }
+    }
+
+    pub fn apply(&self, p: Vector2) -> Vector2 {
+        Vector2::new(self.a * p.x + self.c * p.y + self.e, self.b * p.x + self.d * p.y + self.f)
+    }
+
+    /// `None` when the transform squashes everything onto a line or a point.
+    pub fn inverse(&self) -> Option<Self> {
+        let det = self.a * self.d - self.b * self.c;
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        let (a, b, c, d) = (self.d / det, -self.b / det, -self.c / det, self.a / det);
+        Some(Self {a, b, c, d, e: -(a * self.e + c * self.f), f: -(b * self.e + d * self.f)})
+    }
+
+    /// How much lengths grow at most, used to turn local distances into pixels.
+    ///
+    /// This is the larger singular value of the linear part, so e.g. `scaling(4.0, 1.0)` gives 4.
+    pub fn scale_factor(&self) -> f32 {
+        let sum = self.a * self.a + self.b * self.b + self.c * self.c + self.d * self.d;
+        let det = self.a * self.d - self.b * self.c;
+        let spread = (sum * sum - 4.0 * det * det).max(0.0).sqrt();
+        ((sum + spread) / 2.0).sqrt()
+    }
+
+    /// Whether rects stay rects, i.e. there is no rotation or skew.
+    pub fn is_axis_aligned(&self) -> bool {
+        self.b == 0.0 && self.c == 0.0
+    }
+
+    /// The bounding box of the rect between `min` and `max` after transforming it.
+    pub fn bounds(&self, min: Vector2, max: Vector2) -> (Vector2, Vector2) {
+        let corners = [min, Vector2::new(max.x, min.y), max, Vector2::new(min.x, max.y)].map(|p| self.apply(p));
+        let mut out = (corners[0], corners[0]);
+        for p in &corners[1..] {
+            out.0 = Vector2::new(out.0.x.min(p.x), out.0.y.min(p.y));
+            out.1 = Vector2::new(out.1.x.max(p.x), out.1.y.max(p.y));
+        }
+        out
+    }
+}
+
+/// Coordinates passed to primitives go through the current transform, clips and damage stay in canvas pixels.
+impl Canvas {
+    /// Remembers the current transform until the matching [`Canvas::restore`].
+    pub fn save(&mut self) {
+        self.transform_stack.push(self.transform);
+    }
+
+    /// Goes back to the transform of the last [`Canvas::save`], does nothing without one.
+    pub fn restore(&mut self) {
+        if let Some(transform) = self.transform_stack.pop() {
+            self.transform = transform;
+        }
+    }
+
+    pub fn translate(&mut self, x: f32, y: f32) {
+        self.transform(Transform::translation(x, y));
+    }
+
+    pub fn scale(&mut self, x: f32, y: f32) {
+        self.transform(Transform::scaling(x, y));
+    }
+
+    /// Rotates around the origin of the current coordinates, by `angle` radians.
+    pub fn rotate(&mut self, angle: f32) {
+        self.transform(Transform::rotation(angle));
+    }
+
+    /// Applies `matrix` to coordinates before the current transform.
+    pub fn transform(&mut self, matrix: Transform) {
+        self.transform = matrix.then(&self.transform);
+    }
+
+    pub fn current_transform(&self) -> Transform {
+        self.transform
+    }
+
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+    }
+}
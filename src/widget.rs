@@ -1,24 +1,29 @@
-use std::{any::Any, fmt::Display, fs::File, io::Write, os::fd::{AsFd, AsRawFd}, sync::{Arc, LockResult, RwLock, RwLockWriteGuard}};
+use std::{fmt::Display, sync::{atomic::{AtomicBool, Ordering}, Arc, LockResult, RwLock, RwLockWriteGuard}};
 
-use rusttype::{point, Scale};
-use wayland_client::{globals::{registry_queue_init, GlobalListContents}, protocol::{wl_buffer::WlBuffer, wl_compositor::WlCompositor, wl_keyboard::{KeyState, KeymapFormat, WlKeyboard}, wl_registry::{self, WlRegistry}, wl_seat::{Capability, WlSeat}, wl_shm::WlShm, wl_shm_pool::WlShmPool, wl_surface::WlSurface}, Connection, Dispatch, DispatchError, EventQueue};
+use wayland_client::{globals::{registry_queue_init, GlobalListContents}, protocol::{wl_buffer::WlBuffer, wl_callback::WlCallback, wl_compositor::WlCompositor, wl_keyboard::{KeyState, KeymapFormat, WlKeyboard}, wl_registry::{self, WlRegistry}, wl_seat::{Capability, WlSeat}, wl_shm::{Format, WlShm}, wl_shm_pool::WlShmPool, wl_surface::WlSurface}, backend::WaylandError, Connection, Dispatch, DispatchError, EventQueue, QueueHandle, WEnum};
 use wayland_protocols_wlr::layer_shell::v1::client::{zwlr_layer_shell_v1::ZwlrLayerShellV1, zwlr_layer_surface_v1::ZwlrLayerSurfaceV1};
-use tempfile::tempfile;
 use xkbcommon::xkb::{self, Keycode, Keymap, Keysym};
 
 reexport!(wayland_protocols_wlr::layer_shell::v1::client::zwlr_layer_shell_v1::Layer, "wayland-protocols-wlr-reexport");
 reexport!(wayland_protocols_wlr::layer_shell::v1::client::zwlr_layer_surface_v1::Anchor, "wayland-protocols-wlr-reexport");
 reexport!(wayland_protocols_wlr::layer_shell::v1::client::zwlr_layer_surface_v1::KeyboardInteractivity, "wayland-protocols-wlr-reexport");
-reexport!(rusttype::Font, "rusttype-reexport");
+#[cfg(feature = "rusttype-reexport")]
+pub use rusttype::Font;
 
 
-use crate::{pixel_util::{dist_to_arc, dist_to_line, Vector2}, reexport};
+pub use crate::color::Color;
+pub use crate::font::FontSet;
+pub use crate::paint::Paint;
+use crate::{canvas::Canvas, color::PixelFormat, frame::Frame, pixel_util::Vector2, reexport, shm::BufferPool};
 
 
-type KeyStateRc = Option<Arc<RwLock<xkb::State>>>;
-
 pub struct WidgetData {
-    key_state: KeyStateRc,
+    key_state: Option<xkb::State>,
+    /// Set while the compositor hasn't yet signalled the frame callback of the last commit
+    frame_pending: bool,
+    redraw: Arc<AtomicBool>,
+    /// Formats advertised by `wl_shm`
+    formats: Vec<Format>,
 }
 
 unsafe impl Send for WidgetData {}
@@ -26,12 +31,12 @@ unsafe impl Sync for WidgetData {}
 
 impl Dispatch<WlRegistry, GlobalListContents> for WidgetData {
     fn event(
-        state: &mut Self,
-        proxy: &WlRegistry,
+        _state: &mut Self,
+        _proxy: &WlRegistry,
         event: <WlRegistry as wayland_client::Proxy>::Event,
-        data: &GlobalListContents,
-        conn: &Connection,
-        qhandle: &wayland_client::QueueHandle<Self>,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        _qhandle: &wayland_client::QueueHandle<Self>,
     ) {
         if let wl_registry::Event::Global { name, interface, version } = event {
             println!("[{}] {} (v{})", name, interface, version);
@@ -41,12 +46,12 @@ impl Dispatch<WlRegistry, GlobalListContents> for WidgetData {
 
 impl Dispatch<WlCompositor, ()> for WidgetData {
     fn event(
-        state: &mut Self,
-        proxy: &WlCompositor,
-        event: <WlCompositor as wayland_client::Proxy>::Event,
-        data: &(),
-        conn: &Connection,
-        qhandle: &wayland_client::QueueHandle<Self>,
+        _state: &mut Self,
+        _proxy: &WlCompositor,
+        _event: <WlCompositor as wayland_client::Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &wayland_client::QueueHandle<Self>,
     ) {
         // Has no events
     }
@@ -55,26 +60,51 @@ impl Dispatch<WlCompositor, ()> for WidgetData {
 
 impl Dispatch<WlSurface, ()> for WidgetData {
     fn event(
-        state: &mut Self,
-        proxy: &WlSurface,
-        event: wayland_client::protocol::wl_surface::Event,
-        data: &(),
-        conn: &Connection,
-        qhandle: &wayland_client::QueueHandle<Self>,
+        _state: &mut Self,
+        _proxy: &WlSurface,
+        _event: wayland_client::protocol::wl_surface::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &wayland_client::QueueHandle<Self>,
     ) {
-        use wayland_client::protocol::wl_surface::Event;
         // We don't care about events
     }
 }
 
-impl Dispatch<ZwlrLayerShellV1, ()> for WidgetData {
+/// What a `wl_callback` was requested for.
+#[derive(Copy, Clone)]
+pub enum CallbackKind {
+    /// `wl_surface.frame`, done when the compositor is ready for a new frame
+    Frame,
+    /// `wl_display.sync` sent by a [`RedrawHandle`] to wake up a blocking dispatch
+    Wake,
+}
+
+impl Dispatch<WlCallback, CallbackKind> for WidgetData {
     fn event(
         state: &mut Self,
-        proxy: &ZwlrLayerShellV1,
-        event: wayland_protocols_wlr::layer_shell::v1::client::zwlr_layer_shell_v1::Event,
-        data: &(),
-        conn: &Connection,
-        qhandle: &wayland_client::QueueHandle<Self>,
+        _proxy: &WlCallback,
+        event: wayland_client::protocol::wl_callback::Event,
+        kind: &CallbackKind,
+        _conn: &Connection,
+        _qhandle: &wayland_client::QueueHandle<Self>,
+    ) {
+        use wayland_client::protocol::wl_callback::Event;
+        if let (Event::Done { .. }, CallbackKind::Frame) = (event, kind) {
+            state.frame_pending = false;
+        }
+        // Wake callbacks only exist to return from blocking_dispatch
+    }
+}
+
+impl Dispatch<ZwlrLayerShellV1, ()> for WidgetData {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ZwlrLayerShellV1,
+        _event: wayland_protocols_wlr::layer_shell::v1::client::zwlr_layer_shell_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &wayland_client::QueueHandle<Self>,
     ) {
         // Has no events
     }
@@ -83,15 +113,15 @@ impl Dispatch<ZwlrLayerShellV1, ()> for WidgetData {
 
 impl Dispatch<ZwlrLayerSurfaceV1, ()> for WidgetData {
     fn event(
-        state: &mut Self,
+        _state: &mut Self,
         proxy: &ZwlrLayerSurfaceV1,
         event: wayland_protocols_wlr::layer_shell::v1::client::zwlr_layer_surface_v1::Event,
-        data: &(),
-        conn: &Connection,
-        qhandle: &wayland_client::QueueHandle<Self>,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &wayland_client::QueueHandle<Self>,
     ) {
         use wayland_protocols_wlr::layer_shell::v1::client::zwlr_layer_surface_v1::Event;
-        if let Event::Configure { serial, width, height } = event {
+        if let Event::Configure { serial, .. } = event {
             proxy.ack_configure(serial);
         }
         // We don't care about "Closed" event
@@ -103,62 +133,65 @@ impl Dispatch<ZwlrLayerSurfaceV1, ()> for WidgetData {
 impl Dispatch<WlShm, ()> for WidgetData {
     fn event(
         state: &mut Self,
-        proxy: &WlShm,
+        _proxy: &WlShm,
         event: wayland_client::protocol::wl_shm::Event,
-        data: &(),
-        conn: &Connection,
-        qhandle: &wayland_client::QueueHandle<Self>,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &wayland_client::QueueHandle<Self>,
     ) {
         use wayland_client::protocol::wl_shm::Event;
-        // We don't care about "Format" event
+        if let Event::Format { format: WEnum::Value(format) } = event {
+            state.formats.push(format);
+        }
     }
 }
 
 impl Dispatch<WlShmPool, ()> for WidgetData {
     fn event(
-        state: &mut Self,
-        proxy: &WlShmPool,
-        event: <WlShmPool as wayland_client::Proxy>::Event,
-        data: &(),
-        conn: &Connection,
-        qhandle: &wayland_client::QueueHandle<Self>,
+        _state: &mut Self,
+        _proxy: &WlShmPool,
+        _event: <WlShmPool as wayland_client::Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &wayland_client::QueueHandle<Self>,
     ) {
         // Has no events
     }
 }
 
-impl Dispatch<WlBuffer, ()> for WidgetData {
+impl Dispatch<WlBuffer, Arc<AtomicBool>> for WidgetData {
     fn event(
-        state: &mut Self,
-        proxy: &WlBuffer,
+        _state: &mut Self,
+        _proxy: &WlBuffer,
         event: wayland_client::protocol::wl_buffer::Event,
-        data: &(),
-        conn: &Connection,
-        qhandle: &wayland_client::QueueHandle<Self>,
+        busy: &Arc<AtomicBool>,
+        _conn: &Connection,
+        _qhandle: &wayland_client::QueueHandle<Self>,
     ) {
-        // We don't care about "Release" event
+        use wayland_client::protocol::wl_buffer::Event;
+        if let Event::Release = event {
+            // The compositor is done reading, the pool may reuse this buffer
+            busy.store(false, Ordering::Release);
+        }
     }
 }
 
 impl Dispatch<WlSeat, Events> for WidgetData {
     fn event(
-        state: &mut Self,
+        _state: &mut Self,
         proxy: &WlSeat,
         event: wayland_client::protocol::wl_seat::Event,
         data: &Events,
-        conn: &Connection,
+        _conn: &Connection,
         qhandle: &wayland_client::QueueHandle<Self>,
     ) {
         use wayland_client::protocol::wl_seat::Event;
         println!("Seat Event!");
-        match event {
-            Event::Capabilities { capabilities } => {
-                if capabilities.into_result().unwrap().contains(Capability::Keyboard) {
-                    println!("Capabilities");
-                    proxy.get_keyboard(&qhandle, data.clone());
-                }
-            },
-            _ => {}
+        if let Event::Capabilities { capabilities } = event {
+            if capabilities.into_result().unwrap().contains(Capability::Keyboard) {
+                println!("Capabilities");
+                proxy.get_keyboard(qhandle, data.clone());
+            }
         }
     }
 }
@@ -169,8 +202,8 @@ impl Dispatch<WlKeyboard, Events> for WidgetData {
         proxy: &WlKeyboard,
         event: wayland_client::protocol::wl_keyboard::Event,
         data: &Events,
-        conn: &Connection,
-        qhandle: &wayland_client::QueueHandle<Self>,
+        _conn: &Connection,
+        _qhandle: &wayland_client::QueueHandle<Self>,
     ) {
         use wayland_client::protocol::wl_keyboard::Event;
         match event {
@@ -179,18 +212,18 @@ impl Dispatch<WlKeyboard, Events> for WidgetData {
                     println!("XKB keyboard found!");
                     unsafe {
                         let map = Keymap::new_from_fd(&xkb::Context::new(0), fd, size as usize, format.into_result().unwrap().into(), 0).unwrap().unwrap();
-                        state.key_state = Some(Arc::new(RwLock::new(xkb::State::new(&map))));
+                        state.key_state = Some(xkb::State::new(&map));
                     }
                 } else {
                     println!("Non-XKB keyboard, disconnecting!");
                     proxy.release();
                 }
             },
-            Event::Key { serial, time, key, state: key_state } => {
-                let sym = state.key_state.as_ref().unwrap().read().unwrap();
+            Event::Key { key, state: key_state, .. } => {
+                let (Some(sym), Some(handle)) = (state.key_state.as_ref(), data.handle.as_ref()) else { return };
                 match key_state.into_result().unwrap() {
-                    KeyState::Released => (data.key_released)(sym.key_get_one_sym(Keycode::new(key + 8)), data.comp.clone()),
-                    KeyState::Pressed => (data.key_pressed)(sym.key_get_one_sym(Keycode::new(key + 8)), data.comp.clone()),
+                    KeyState::Released => (data.key_released)(sym.key_get_one_sym(Keycode::new(key + 8)), handle),
+                    KeyState::Pressed => (data.key_pressed)(sym.key_get_one_sym(Keycode::new(key + 8)), handle),
                     _ => {},
                 }
             },
@@ -199,28 +232,90 @@ impl Dispatch<WlKeyboard, Events> for WidgetData {
     }
 }
 
+pub type KeyCallback = Arc<dyn Fn(Keysym, &WidgetHandle) + Send + Sync>;
+
+/// Key callbacks, called with the key's symbol and a [`WidgetHandle`].
+///
+/// Callbacks run while [`WidgetComponents::run`] holds the widget's lock, so they act on the widget through the handle.
 #[derive(Clone)]
 pub struct Events {
-    pub key_pressed: Arc<dyn Fn(Keysym, ComponentsRc) + Send + Sync>,
-    pub key_released: Arc<dyn Fn(Keysym, ComponentsRc) + Send + Sync>,
-    pub comp: ComponentsRc,
+    pub key_pressed: KeyCallback,
+    pub key_released: KeyCallback,
+    handle: Option<WidgetHandle>,
+    /// Stops the widget's event loop, see [`Events::close_handle`]
+    pub close: CloseHandle,
 }
 
 impl Events {
     pub fn none() -> Self {
-        Self {key_pressed: Arc::new(|_,_| ()), key_released: Arc::new(|_,_| ()), comp: None}
+        Self {key_pressed: Arc::new(|_,_| ()), key_released: Arc::new(|_,_| ()), handle: None, close: CloseHandle::new()}
     }
-    pub fn new<F1: Fn(Keysym, ComponentsRc) + Send + Sync + 'static, F2: Fn(Keysym, ComponentsRc) + Send + Sync + 'static>(key_pressed: F1, key_released: F2) -> Self {
+    pub fn new<F1: Fn(Keysym, &WidgetHandle) + Send + Sync + 'static, F2: Fn(Keysym, &WidgetHandle) + Send + Sync + 'static>(key_pressed: F1, key_released: F2) -> Self {
         Self {
             key_pressed: Arc::new(key_pressed),
             key_released: Arc::new(key_released),
-            comp: None,
+            handle: None,
+            close: CloseHandle::new(),
         }
     }
+
+    /// Closes the widget through `handle`, so code outside the callbacks can close it before the widget exists.
+    pub fn close_handle(mut self, handle: CloseHandle) -> Self {
+        self.close = handle;
+        self
+    }
+}
+
+/// Closes or redraws a widget without its lock, passed to key callbacks.
+#[derive(Clone)]
+pub struct WidgetHandle {
+    close: CloseHandle,
+    redraw: RedrawHandle,
+}
+
+impl WidgetHandle {
+    pub fn close(&self) {
+        self.close.close();
+    }
+
+    /// Marks the widget as dirty, [`WidgetComponents::run`] redraws it on the next frame.
+    pub fn request_redraw(&self) -> Result<(), WidgetError> {
+        self.redraw.request_redraw()
+    }
+
+    pub fn close_handle(&self) -> CloseHandle {
+        self.close.clone()
+    }
+
+    pub fn redraw_handle(&self) -> RedrawHandle {
+        self.redraw.clone()
+    }
+}
+
+/// Stops [`WidgetComponents::run`] without locking the widget.
+///
+/// The loop checks it after each dispatch, so closing from another thread takes effect once the widget receives an event.
+/// It replaces the former public `WidgetComponents::running` flag: call [`CloseHandle::close`] instead of setting it to `false`.
+#[derive(Clone, Default)]
+pub struct CloseHandle {
+    closed: Arc<AtomicBool>,
+}
+
+impl CloseHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire)
+    }
 }
 
 pub struct WidgetBuilder<'a> {
-    buffer: Vec<u8>,
     conn: &'a Connection,
     width: u32,
     height: u32,
@@ -231,6 +326,8 @@ pub struct WidgetBuilder<'a> {
     margin: Margin,
     kb_interactivity: KeyboardInteractivity,
     events: Events,
+    opaque: bool,
+    deep_color: bool,
 }
 
 
@@ -238,7 +335,6 @@ pub struct WidgetBuilder<'a> {
 impl<'a> WidgetBuilder<'a> {
     pub fn new(conn: &'a Connection, width: u32, height: u32) -> Self {
         Self {
-            buffer: vec![],            
             conn,
             width,
             height,
@@ -249,21 +345,23 @@ impl<'a> WidgetBuilder<'a> {
             margin: Margin {top: 0, bottom: 0, left: 0, right: 0},
             kb_interactivity: KeyboardInteractivity::None,
             events: Events::none(),
+            opaque: false,
+            deep_color: false,
         }
     }
 
     pub fn layer(mut self, layer: Layer) -> Self {
-        self.layer = layer.into();
+        self.layer = layer;
         self
     }
 
     pub fn anchor(mut self, anchor: Anchor) -> Self {
-        self.anchor = anchor.into();
+        self.anchor = anchor;
         self
     }
 
     pub fn exclusive_edge(mut self, anchor: Anchor) -> Self {
-        self.exclusive_edge = anchor.into();
+        self.exclusive_edge = anchor;
         self
     }
 
@@ -283,6 +381,18 @@ impl<'a> WidgetBuilder<'a> {
         self
     }
 
+    /// Widgets that never show what's below them use an `Xrgb` format, sparing the compositor the blending.
+    pub fn opaque(mut self, opaque: bool) -> Self {
+        self.opaque = opaque;
+        self
+    }
+
+    /// Uses a 10 bit per channel format when the compositor supports one, falling back to 8 bits.
+    pub fn deep_color(mut self, deep_color: bool) -> Self {
+        self.deep_color = deep_color;
+        self
+    }
+
     pub fn build(&self) -> Widget<'a> {
         Widget { 
             conn: self.conn, 
@@ -296,38 +406,8 @@ impl<'a> WidgetBuilder<'a> {
             margin: self.margin,
             kb_interactivity: self.kb_interactivity,
             events: self.events.clone(),
-        }
-    }
-}
-
-#[derive(Copy, Clone)]
-pub struct Color {
-    r: u8,
-    g: u8,
-    b: u8,
-    a: u8,
-}
-
-impl From<u32> for Color {
-    fn from(value: u32) -> Self {
-        let bytes = value.to_ne_bytes();
-        
-        Self {
-           r: bytes[0],
-           g: bytes[1],
-           b: bytes[2],
-           a: bytes[3], 
-        }
-    }
-}
-
-impl Color {
-    pub fn lerp(&self, bg: Self, value: f32) -> Self {
-        Self {
-            r: (value * self.r as f32 + (1.0 - value) * bg.r as f32).round() as u8,
-            g: (value * self.g as f32 + (1.0 - value) * bg.g as f32).round() as u8,
-            b: (value * self.b as f32 + (1.0 - value) * bg.b as f32).round() as u8,
-            a: (value * self.a as f32 + (1.0 - value) * bg.a as f32).round() as u8,
+            opaque: self.opaque,
+            deep_color: self.deep_color,
         }
     }
 }
@@ -340,6 +420,12 @@ pub struct Margin {
     right: u32
 }
 
+impl Default for Margin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Margin {
     pub fn new() -> Self {
         Self {
@@ -376,128 +462,135 @@ pub struct WidgetComponents {
     pub surface: WlSurface,
     pub shm: WlShm,
     pub queue: EventQueue<WidgetData>,
-    pub buffer: Vec<u8>,
-    width: u32,
-    height: u32,
+    conn: Connection,
+    pub canvas: Canvas,
+    pool: BufferPool,
     data: WidgetData,
-    pub running: bool,
+    close: CloseHandle,
 }
 
 impl WidgetComponents {
-    fn get_buffer(&self) -> Result<File, WidgetError> {
+    /// Attaches the canvas to the surface and commits its damaged regions.
+    pub fn present(&mut self) -> Result<(), WidgetError> {
+        if self.canvas.width() != self.pool.width() || self.canvas.height() != self.pool.height() || Format::from(self.canvas.format()) != self.pool.format() {
+            return Err(WidgetError::CanvasMismatch);
+        }
+        if self.canvas.damaged().is_empty() {
+            // Nothing changed, keep showing the current buffer
+            return Ok(());
+        }
+
         let qh = self.queue.handle();
-        let file = tempfile()?;
-        let size = self.buffer.len();
-        file.set_len(size as u64)?;
-        let pool = self.shm.create_pool(file.as_fd(), size as i32, &qh, ());
-        let buffer = pool.create_buffer(0, self.width as i32, self.height as i32, self.width as i32 * 4, wayland_client::protocol::wl_shm::Format::Argb8888, &qh, ());
-
-        self.surface.attach(Some(&buffer), 0, 0);
-        self.surface.damage(0, 0, self.width as i32, self.height as i32);
+        let slot = loop {
+            if let Some(slot) = self.pool.acquire(&qh)? {
+                break slot;
+            }
+            // Every buffer is still held by the compositor, wait for a release
+            self.queue.blocking_dispatch(&mut self.data)?;
+        };
+        let buffer = self.pool.write(slot, self.canvas.data())?;
+
+        self.surface.attach(Some(buffer), 0, 0);
+        for rect in self.canvas.take_damage() {
+            self.surface.damage_buffer(rect.x, rect.y, rect.width, rect.height);
+        }
+        self.surface.frame(&qh, CallbackKind::Frame);
+        self.data.frame_pending = true;
         self.surface.commit();
 
-        Ok(file)
+        Ok(())
     }
     
-    pub fn draw_text<F: Fn(u32, char) -> Color>(&mut self, text: String, pos: Vector2, size: f32, font: Font, colorf: F, bg: Color) -> Result<(), WidgetError> {
-        let mut file = self.get_buffer()?;
+    /// Starts a batch of drawing operations that is committed once by [`Frame::present`].
+    pub fn begin_frame(&mut self) -> Frame<'_> {
+        Frame::new(self)
+    }
 
-        
-        let scale = Scale::uniform(size);  // Font size
-        let start = point(pos.x, pos.y);     // Position to start rendering text
-
-        let mut chars = text.chars();
-
-        // Render each character of the text
-        for (index, glyph) in Into::<Font>::into(font).layout(&text, scale, start).enumerate() {
-            let char = chars.next().unwrap();
-            glyph.draw(|x, y, v| {
-                let rect = glyph.pixel_bounding_box().unwrap();
-                let ny = rect.min.y as i32 + y as i32;
-                let nx = rect.min.x as i32 + x as i32;
-                if ny < 0 || nx < 0 {
-                    return;
-                }
-                let pixel_index = (ny as usize * self.width as usize + nx as usize) * 4;
-                let color = colorf(index as u32, char).lerp(bg, v);
-                if pixel_index + 4 < self.buffer.len() {
-                    self.buffer[pixel_index] = color.r;     // Red
-                    self.buffer[pixel_index + 1] = color.g; // Green
-                    self.buffer[pixel_index + 2] = color.b; // Blue
-                    self.buffer[pixel_index + 3] = color.a; // Alpha (fully opaque)
-                };
-            });
-        }
+    pub fn draw_text<'f, S: Into<FontSet<'f>>, F: Fn(u32, char) -> Paint>(&mut self, text: String, pos: Vector2, size: f32, font: S, colorf: F, bg: Paint) -> Result<(), WidgetError> {
+        let mut frame = self.begin_frame();
+        frame.draw_text(text, pos, size, font, colorf, bg);
+        frame.present()
+    }
 
-        file.write_all(&self.buffer)?;
+    pub fn draw_rect(&mut self, paint: Paint, pos: Vector2, size: Vector2) -> Result<(), WidgetError> {
+        let mut frame = self.begin_frame();
+        frame.draw_rect(paint, pos, size);
+        frame.present()
+    }
 
-        Ok(())
+    pub fn draw_line(&mut self, paint: Paint, a: Vector2, b: Vector2, thickness: f32) -> Result<(), WidgetError> {
+        let mut frame = self.begin_frame();
+        frame.draw_line(paint, a, b, thickness);
+        frame.present()
     }
 
-    pub fn draw_rect(&mut self, color: Color, pos: Vector2, size: Vector2) -> Result<(), WidgetError> {
-        let mut file = self.get_buffer()?;
-        let pos_x = pos.x.round() as i32;
-        let pos_y = pos.y.round() as i32;
+    pub fn draw_arc(&mut self, paint: Paint, center: Vector2, radius: f32, start: f32, end: f32, thickness: f32) -> Result<(), WidgetError> {
+        let mut frame = self.begin_frame();
+        frame.draw_arc(paint, center, radius, start, end, thickness);
+        frame.present()
+    }
 
-        let size_x = size.x.round() as i32;
-        let size_y = size.y.round() as i32;
+    pub fn draw_where<F : Fn(Vector2) -> bool>(&mut self, paint: Paint, predicate: F, min: Vector2, max: Vector2) -> Result<(), WidgetError> {
+        let mut frame = self.begin_frame();
+        frame.draw_where(paint, predicate, min, max);
+        frame.present()
+    }
 
-        for x in pos_x.max(0)..(pos_x + size_x) {
-            for y in pos_y.max(0)..(pos_y + size_y) {
-                let pixel_index = (y as usize * self.width as usize + x as usize) * 4;
-                if pixel_index + 4 > self.buffer.len() {
-                    continue;
-                }
-                self.buffer[pixel_index] = color.r;
-                self.buffer[pixel_index + 1] = color.g;
-                self.buffer[pixel_index + 2] = color.b;
-                self.buffer[pixel_index + 3] = color.a;
-            }
-        }
-        file.write_all(&self.buffer)?;
+    pub fn draw_sdf<F : Fn(Vector2) -> f32>(&mut self, paint: Paint, dist: F, min: Vector2, max: Vector2) -> Result<(), WidgetError> {
+        let mut frame = self.begin_frame();
+        frame.draw_sdf(paint, dist, min, max);
+        frame.present()
+    }
 
-        Ok(())
+    /// Marks the widget as dirty, [`WidgetComponents::run`] redraws it on the next frame.
+    pub fn request_redraw(&self) {
+        self.data.redraw.store(true, Ordering::Release);
     }
 
-    pub fn draw_line(&mut self, color: Color, a: Vector2, b: Vector2, thickness: f32) -> Result<(), WidgetError> {
-        let margin = Vector2::new(thickness, thickness);
-        let min = Vector2::new(a.x.min(b.x), a.y.min(b.y)) - margin;
-        let max = Vector2::new(a.x.max(b.x), a.y.max(b.y)) + margin;
-        self.draw_where(color, |v| dist_to_line(a, b, v) < thickness, min, max)
+    /// The pixel formats advertised by the compositor.
+    pub fn supported_formats(&self) -> &[Format] {
+        &self.data.formats
     }
 
-    pub fn draw_arc(&mut self, color: Color, center: Vector2, radius: f32, start: f32, end: f32, thickness: f32) -> Result<(), WidgetError> {
-        let margin = Vector2::new(thickness + radius, thickness + radius);
-        let min = center - margin;
-        let max = center + margin;
-        self.draw_where(color, |v| dist_to_arc(center, radius, start, end, v) < thickness, min, max)        
+    /// A handle that can request redraws from other threads, see [`RedrawHandle`].
+    pub fn redraw_handle(&self) -> RedrawHandle {
+        RedrawHandle {redraw: self.data.redraw.clone(), conn: self.conn.clone(), qh: self.queue.handle()}
     }
 
-    pub fn draw_where<F : Fn(Vector2) -> bool>(&mut self, color: Color, predicate: F, min: Vector2, max: Vector2) -> Result<(), WidgetError> {
-        let mut file = self.get_buffer()?;
-        
-        for x in min.x.round().max(0.0) as i32..max.x.round() as i32 {
-            for y in min.y.round().max(0.0) as i32..max.y.round() as i32 {
-                let pixel_index = (y as usize * self.width as usize + x as usize) * 4;
-                if predicate(Vector2::new(x as f32, y as f32)) {
-                    self.buffer[pixel_index] = color.r;
-                    self.buffer[pixel_index + 1] = color.g;
-                    self.buffer[pixel_index + 2] = color.b;
-                    self.buffer[pixel_index + 3] = color.a;
-                }
+    /// Whether a redraw was requested and the compositor is ready for a new frame.
+    pub fn needs_redraw(&self) -> bool {
+        self.data.redraw.load(Ordering::Acquire) && !self.data.frame_pending
+    }
+
+    /// Runs the event loop until the widget is closed, calling `draw` whenever a redraw was
+    /// requested and the compositor signalled it is ready for the next frame.
+    ///
+    /// Between frames the loop sleeps in `blocking_dispatch`, so an idle widget uses no CPU.
+    /// The first frame is drawn right away.
+    pub fn run<F: FnMut(&mut Frame)>(&mut self, mut draw: F) -> Result<(), WidgetError> {
+        self.request_redraw();
+        while !self.close.is_closed() {
+            if self.needs_redraw() {
+                self.data.redraw.store(false, Ordering::Release);
+                let mut frame = self.begin_frame();
+                draw(&mut frame);
+                frame.present()?;
             }
+            self.queue.blocking_dispatch(&mut self.data)?;
         }
-
-        file.write_all(&self.buffer)?;
-
         Ok(())
     }
 
-    pub fn close(&mut self) -> Option<()> {
-        self.running = false;
+    pub fn close(&self) -> Option<()> {
+        self.close.close();
         Some(())
     }
 
+    /// A handle that closes the widget without its lock, see [`CloseHandle`].
+    pub fn close_handle(&self) -> CloseHandle {
+        self.close.clone()
+    }
+
     pub fn update(&mut self) -> Result<(), WidgetError> {
         self.queue.dispatch_pending(&mut self.data)?;
         Ok(())
@@ -515,6 +608,24 @@ impl WidgetComponents {
     }
 }
 
+/// Requests redraws of a widget from any thread, e.g. a timer ticking every second.
+#[derive(Clone)]
+pub struct RedrawHandle {
+    redraw: Arc<AtomicBool>,
+    conn: Connection,
+    qh: QueueHandle<WidgetData>,
+}
+
+impl RedrawHandle {
+    pub fn request_redraw(&self) -> Result<(), WidgetError> {
+        self.redraw.store(true, Ordering::Release);
+        // The event loop may be asleep, a sync roundtrip makes the compositor wake it up
+        self.conn.display().sync(&self.qh, CallbackKind::Wake);
+        self.conn.flush()?;
+        Ok(())
+    }
+}
+
 pub struct Widget<'a> {
     conn: &'a Connection,
     width: u32,
@@ -526,6 +637,8 @@ pub struct Widget<'a> {
     margin: Margin,
     kb_interactivity: KeyboardInteractivity,
     events: Events,
+    opaque: bool,
+    deep_color: bool,
     
     pub comp: ComponentsRc,    
 }
@@ -534,7 +647,17 @@ pub struct Widget<'a> {
 pub enum WidgetError {
     StdIO(std::io::Error),
     UninitializedWidget,
+    CanvasMismatch,
+    PngEncoding(png::EncodingError),
+    PngDecoding(png::DecodingError),
+    JpegDecoding(jpeg_decoder::Error),
+    InvalidImage,
+    SvgParsing(usvg::Error),
+    FontNotFound(String),
+    InvalidFontQuery(String),
+    InvalidFont,
     WlDispatch(DispatchError),
+    WlConnection(WaylandError),
 }
 
 impl From<std::io::Error> for WidgetError {
@@ -543,19 +666,59 @@ impl From<std::io::Error> for WidgetError {
     }
 }
 
+impl From<png::EncodingError> for WidgetError {
+    fn from(value: png::EncodingError) -> Self {
+        Self::PngEncoding(value)
+    }
+}
+
+impl From<png::DecodingError> for WidgetError {
+    fn from(value: png::DecodingError) -> Self {
+        Self::PngDecoding(value)
+    }
+}
+
+impl From<jpeg_decoder::Error> for WidgetError {
+    fn from(value: jpeg_decoder::Error) -> Self {
+        Self::JpegDecoding(value)
+    }
+}
+
+impl From<usvg::Error> for WidgetError {
+    fn from(value: usvg::Error) -> Self {
+        Self::SvgParsing(value)
+    }
+}
+
 impl From<DispatchError> for WidgetError {
     fn from(value: DispatchError) -> Self {
         Self::WlDispatch(value)
     }
 }
 
+impl From<WaylandError> for WidgetError {
+    fn from(value: WaylandError) -> Self {
+        Self::WlConnection(value)
+    }
+}
+
  
 impl Display for WidgetError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::UninitializedWidget => f.write_str("Cannot draw to uninitialized widgets!"),
+            Self::CanvasMismatch => f.write_str("Canvas size or format doesn't match the widget!"),
             Self::StdIO(e) => e.fmt(f),
-            Self::WlDispatch(e) => e.fmt(f)
+            Self::PngEncoding(e) => e.fmt(f),
+            Self::PngDecoding(e) => e.fmt(f),
+            Self::JpegDecoding(e) => e.fmt(f),
+            Self::InvalidImage => f.write_str("Image data is malformed or in an unsupported format!"),
+            Self::SvgParsing(e) => e.fmt(f),
+            Self::FontNotFound(query) => write!(f, "No font matches \"{query}\"!"),
+            Self::InvalidFontQuery(query) => write!(f, "Font query \"{query}\" is malformed!"),
+            Self::InvalidFont => f.write_str("Font data is malformed or in an unsupported format!"),
+            Self::WlDispatch(e) => e.fmt(f),
+            Self::WlConnection(e) => e.fmt(f),
         }
     }
 }
@@ -568,16 +731,20 @@ impl std::error::Error for WidgetError {}
 impl<'a> Widget<'a> {
     pub fn create_surface(&mut self, namespace: String) -> Result<(), WidgetError> {
         println!("Creating surface...");
-        let (globals, mut queue) = registry_queue_init::<WidgetData>(&self.conn).unwrap();
+        let (globals, mut queue) = registry_queue_init::<WidgetData>(self.conn).unwrap();
         let qh = queue.handle();
 
-        let mut state = WidgetData {key_state: None};
+        let mut state = WidgetData {key_state: None, frame_pending: false, redraw: Arc::new(AtomicBool::new(false)), formats: vec![]};
 
         queue.roundtrip(&mut state).unwrap();
 
         let compositor : WlCompositor = globals.bind(&qh, 5..=6, ()).unwrap();
         let layer_shell : ZwlrLayerShellV1 = globals.bind(&qh, 0..=5 , ()).unwrap();
         let shm : WlShm = globals.bind(&qh, 0..=1, ()).unwrap();
+        // Collect the advertised formats before picking one
+        queue.roundtrip(&mut state).unwrap();
+        let format = self.pick_format(&state.formats);
+        let pool = BufferPool::new(&shm, self.width, self.height, format, &qh)?;
 
 
         let surface = compositor.create_surface(&qh, ());
@@ -595,9 +762,9 @@ impl<'a> Widget<'a> {
         queue.roundtrip(&mut state).unwrap();
 
         
-        self.comp = Some(Arc::new(RwLock::new(WidgetComponents { running: true, surface, shm, queue, buffer: vec![0u8; (self.width * self.height * 4) as usize], width: self.width, height: self.height, data: state })));
-
-        self.events.comp = self.comp.clone();
+        let comp = WidgetComponents { close: self.events.close.clone(), surface, shm, queue, conn: self.conn.clone(), pool, canvas: Canvas::with_format(self.width, self.height, format), data: state };
+        self.events.handle = Some(WidgetHandle {close: comp.close_handle(), redraw: comp.redraw_handle()});
+        self.comp = Some(Arc::new(RwLock::new(comp)));
 
         let _seat : WlSeat = globals.bind(&qh, 8..=9, self.events.clone()).unwrap();
 
@@ -605,7 +772,21 @@ impl<'a> Widget<'a> {
         Ok(())
     }
 
-    pub fn get_comp(&mut self) -> LockResult<RwLockWriteGuard<WidgetComponents>> {
+    fn pick_format(&self, supported: &[Format]) -> PixelFormat {
+        let (deep, shallow) = if self.opaque {
+            (PixelFormat::Xrgb2101010, PixelFormat::Xrgb8888)
+        } else {
+            (PixelFormat::Argb2101010, PixelFormat::Argb8888)
+        };
+        // Argb8888 and Xrgb8888 are always supported
+        if self.deep_color && supported.contains(&deep.into()) {
+            deep
+        } else {
+            shallow
+        }
+    }
+
+    pub fn get_comp(&mut self) -> LockResult<RwLockWriteGuard<'_, WidgetComponents>> {
         self.comp.as_mut().unwrap().write()
     }
 